@@ -1,27 +1,340 @@
-﻿use std::collections::HashMap;
+﻿use crate::util::matrix::Matrix;
+use num_bigint::BigUint;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
 use std::fs;
+use std::ops::Add;
 
-fn split_stone(stone: u64) -> Vec<u64> {
-    let str = stone.to_string();
-    let (left, right) = str.split_at(str.len() / 2);
-    vec![left.parse().unwrap(), right.parse().unwrap()]
-}
-fn split_stone_2(stone: u64) -> (u64, u64) {
+fn split_stone(stone: u64) -> (u64, u64) {
     let str = stone.to_string();
     let (left, right) = str.split_at(str.len() / 2);
     (left.parse().unwrap(), right.parse().unwrap())
 }
-fn blink(stones: &Vec<u64>) -> Vec<u64> {
-    stones.iter()
-        .flat_map(|stone| match stone {
-            0 => vec![1],
-            n => if n.ilog10() % 2 == 1 { split_stone(*stone) } else { vec![stone * 2024] },
+
+/// One way a stone can change on a blink. Rules are tried in order and the first one
+/// that matches decides what the stone turns into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// A stone engraved with the first number is replaced by one with the second
+    Replace(u64, u64),
+    /// A stone with an even number of digits splits into its left and right halves
+    SplitEvenDigits,
+    /// Any stone is replaced by one engraved with its number times the factor
+    Multiply(u64),
+}
+
+/// A `Multiply` rule took a stone past `u64::MAX`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoneOverflow {
+    pub stone: u64,
+    pub factor: u64,
+}
+
+impl Display for StoneOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "stone {} times {} does not fit in a u64",
+            self.stone, self.factor
+        )
+    }
+}
+
+impl Rule {
+    fn apply(&self, stone: u64) -> Result<Option<Vec<u64>>, StoneOverflow> {
+        Ok(match *self {
+            Rule::Replace(from, to) => (stone == from).then(|| vec![to]),
+            Rule::SplitEvenDigits => (stone != 0 && stone.ilog10() % 2 == 1).then(|| {
+                let (l, r) = split_stone(stone);
+                vec![l, r]
+            }),
+            Rule::Multiply(factor) => match stone.checked_mul(factor) {
+                Some(product) => Some(vec![product]),
+                None => return Err(StoneOverflow { stone, factor }),
+            },
         })
-        .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Rules {
+        Rules(rules)
+    }
+
+    /// The rules from the puzzle: `0 => 1`, split even digits, otherwise `* 2024`
+    pub fn puzzle() -> Rules {
+        Rules(vec![
+            Rule::Replace(0, 1),
+            Rule::SplitEvenDigits,
+            Rule::Multiply(2024),
+        ])
+    }
+
+    /// Stones that `stone` turns into after one blink. A stone no rule matches stays as it is.
+    pub fn blink(&self, stone: u64) -> Result<Vec<u64>, StoneOverflow> {
+        for rule in &self.0 {
+            if let Some(stones) = rule.apply(stone)? {
+                return Ok(stones);
+            }
+        }
+        Ok(vec![stone])
+    }
+}
+
+/// Anything stones can be counted in, from `u64` up to `BigUint`
+pub trait Count: Clone + Add<Output = Self> + From<u8> {}
+
+impl<T: Clone + Add<Output = T> + From<u8>> Count for T {}
+
+/// Number of stones engraved with each value. Stones with the same value evolve the same
+/// way, so a blink only has to look at each distinct value once.
+pub struct Simulation<T = u64> {
+    rules: Rules,
+    histogram: HashMap<u64, T>,
+    blinks: usize,
+}
+
+impl<T: Count> Simulation<T> {
+    pub fn new(stones: &[u64], rules: Rules) -> Simulation<T> {
+        let mut histogram = HashMap::new();
+        for &stone in stones {
+            add_stones(&mut histogram, stone, T::from(1));
+        }
+        Simulation {
+            rules,
+            histogram,
+            blinks: 0,
+        }
+    }
+
+    /// Leaves the stones as they were if any of them overflows.
+    pub fn blink(&mut self) -> Result<(), StoneOverflow> {
+        let mut next = HashMap::with_capacity(self.histogram.len());
+        for (&stone, count) in &self.histogram {
+            for new_stone in self.rules.blink(stone)? {
+                add_stones(&mut next, new_stone, count.clone());
+            }
+        }
+        self.histogram = next;
+        self.blinks += 1;
+        Ok(())
+    }
+
+    /// Blinks `blinks` more times, returning the stats after each blink
+    pub fn run(&mut self, blinks: usize) -> Result<Vec<BlinkStats<T>>, StoneOverflow> {
+        (0..blinks)
+            .map(|_| {
+                self.blink()?;
+                Ok(self.stats())
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> BlinkStats<T> {
+        BlinkStats {
+            blink: self.blinks,
+            distinct: self.distinct(),
+            total: self.total(),
+        }
+    }
+
+    pub fn blinks(&self) -> usize {
+        self.blinks
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.histogram.len()
+    }
+
+    pub fn total(&self) -> T {
+        self.histogram
+            .values()
+            .fold(T::from(0), |acc, count| acc + count.clone())
+    }
+
+    /// Number of stones currently engraved with `stone`
+    pub fn count(&self, stone: u64) -> T {
+        self.histogram
+            .get(&stone)
+            .cloned()
+            .unwrap_or_else(|| T::from(0))
+    }
+}
+
+fn add_stones<T: Count>(histogram: &mut HashMap<u64, T>, stone: u64, count: T) {
+    match histogram.get_mut(&stone) {
+        Some(existing) => *existing = existing.clone() + count,
+        None => {
+            histogram.insert(stone, count);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlinkStats<T> {
+    pub blink: usize,
+    pub distinct: usize,
+    pub total: T,
+}
+
+/// Every stone value reachable from some start stones, closed under blinking, with the
+/// stones each value turns into.
+pub struct StoneGraph {
+    values: Vec<u64>,
+    ids: HashMap<u64, usize>,
+    children: Vec<Vec<usize>>,
+}
+
+/// Blink after which the set of distinct values on the table starts repeating, and how
+/// many blinks each repeat takes. A period of 1 means the set has stopped changing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settling {
+    pub blink: usize,
+    pub period: usize,
+}
+
+/// Why the closed set of stone values could not be built
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoneGraphError {
+    /// More distinct values than the limit turned up, so the rules probably never close
+    TooManyValues(usize),
+    Overflow(StoneOverflow),
+}
+
+impl Display for StoneGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoneGraphError::TooManyValues(limit) => {
+                write!(f, "more than {limit} distinct stone values")
+            }
+            StoneGraphError::Overflow(overflow) => overflow.fmt(f),
+        }
+    }
+}
+
+impl From<StoneOverflow> for StoneGraphError {
+    fn from(overflow: StoneOverflow) -> Self {
+        StoneGraphError::Overflow(overflow)
+    }
 }
 
+impl StoneGraph {
+    /// Most distinct values `new` collects before giving up on the rules closing
+    pub const MAX_VALUES: usize = 1_000_000;
+
+    pub fn new(stones: &[u64], rules: &Rules) -> Result<StoneGraph, StoneGraphError> {
+        StoneGraph::with_limit(stones, rules, StoneGraph::MAX_VALUES)
+    }
+
+    /// Fails instead of running forever if the stones reach more than `limit` values.
+    pub fn with_limit(
+        stones: &[u64],
+        rules: &Rules,
+        limit: usize,
+    ) -> Result<StoneGraph, StoneGraphError> {
+        let mut blinks: HashMap<u64, Vec<u64>> = HashMap::new();
+        let mut stack: Vec<u64> = stones.to_vec();
+        while let Some(stone) = stack.pop() {
+            if blinks.contains_key(&stone) {
+                continue;
+            }
+            let children = rules.blink(stone)?;
+            stack.extend(&children);
+            blinks.insert(stone, children);
+            if blinks.len() > limit {
+                return Err(StoneGraphError::TooManyValues(limit));
+            }
+        }
+
+        let mut values: Vec<u64> = blinks.keys().copied().collect();
+        values.sort_unstable();
+        let ids: HashMap<u64, usize> = values.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+        let children = values
+            .iter()
+            .map(|stone| blinks[stone].iter().map(|child| ids[child]).collect())
+            .collect();
+
+        Ok(StoneGraph {
+            values,
+            ids,
+            children,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
 
-fn blink_stone(stone: u64, max_level: i32, level: i32, memoize: &mut HashMap<(u64, i32), u64>) -> u64 {
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+
+    /// Matrix whose cell `(to, from)` is how many `to` stones a single `from` stone turns
+    /// into, so multiplying it with a histogram vector is one blink.
+    pub fn transition_matrix(&self) -> Matrix {
+        let mut matrix = Matrix::zero(self.len());
+        for (from, children) in self.children.iter().enumerate() {
+            for &to in children {
+                let count = matrix.get(to, from) + 1u8;
+                matrix.set(to, from, count);
+            }
+        }
+        matrix
+    }
+
+    /// Number of stones after `blinks` blinks, from the `blinks`th power of the transition
+    /// matrix. Only takes `O(log blinks)` matrix products, but each one is cubic in the
+    /// size of the closed set.
+    pub fn count_after(&self, stones: &[u64], blinks: u64) -> BigUint {
+        self.sum_columns(&self.transition_matrix().pow(blinks), stones)
+    }
+
+    /// Number of stones after `blinks` blinks modulo `modulus`, which stays cheap for
+    /// blink counts far too large for the exact count to fit in memory.
+    pub fn count_after_mod(&self, stones: &[u64], blinks: u64, modulus: &BigUint) -> BigUint {
+        self.sum_columns(&self.transition_matrix().pow_mod(blinks, modulus), stones) % modulus
+    }
+
+    fn sum_columns(&self, matrix: &Matrix, stones: &[u64]) -> BigUint {
+        let totals: Vec<BigUint> = (0..self.len())
+            .map(|col| (0..self.len()).map(|row| matrix.get(row, col)).sum())
+            .collect();
+        stones
+            .iter()
+            .map(|stone| &totals[*self.ids.get(stone).expect("Stone is not in the graph")])
+            .sum()
+    }
+
+    /// Follows the set of distinct values from `stones` until it repeats.
+    pub fn settle(&self, stones: &[u64]) -> Settling {
+        let mut present: BTreeSet<usize> = stones.iter().map(|stone| self.ids[stone]).collect();
+        let mut seen = HashMap::new();
+
+        for blink in 0.. {
+            if let Some(first) = seen.insert(present.clone(), blink) {
+                return Settling {
+                    blink: first,
+                    period: blink - first,
+                };
+            }
+            present = present
+                .iter()
+                .flat_map(|&id| self.children[id].iter().copied())
+                .collect();
+        }
+        unreachable!()
+    }
+}
+
+fn blink_stone(
+    stone: u64,
+    max_level: i32,
+    level: i32,
+    memoize: &mut HashMap<(u64, i32), u64>,
+) -> u64 {
     if level >= max_level {
         return 1;
     }
@@ -30,11 +343,14 @@ fn blink_stone(stone: u64, max_level: i32, level: i32, memoize: &mut HashMap<(u6
     if !memoize.contains_key(&(stone, level)) {
         let value = match stone {
             0 => blink_stone(1, max_level, level + 1, &mut memoize),
-            _ => if stone.ilog10() % 2 == 1 {
-                let (l, r) = split_stone_2(stone);
-                blink_stone(l, max_level, level + 1, &mut memoize) + blink_stone(r, max_level, level + 1, &mut memoize)
-            } else {
-                blink_stone(stone * 2024, max_level, level + 1, &mut memoize)
+            _ => {
+                if stone.ilog10() % 2 == 1 {
+                    let (l, r) = split_stone(stone);
+                    blink_stone(l, max_level, level + 1, &mut memoize)
+                        + blink_stone(r, max_level, level + 1, &mut memoize)
+                } else {
+                    blink_stone(stone * 2024, max_level, level + 1, &mut memoize)
+                }
             }
         };
         memoize.insert((stone, level), value);
@@ -44,22 +360,27 @@ fn blink_stone(stone: u64, max_level: i32, level: i32, memoize: &mut HashMap<(u6
     }
 }
 
-fn part1(stones: &Vec<u64>) -> i32 {
-    let mut stones = stones.clone();
-    for i in 0..25 {
-        stones = blink(&stones);
-    }
-    stones.len() as i32
+fn count_stones<T: Count>(stones: &[u64], blinks: usize) -> T {
+    let mut simulation = Simulation::new(stones, Rules::puzzle());
+    // Under the puzzle rules every stone ends up in a closed set of small values, see
+    // `StoneGraph`
+    simulation
+        .run(blinks)
+        .expect("The puzzle rules keep stones small");
+    simulation.total()
+}
+
+fn part1(stones: &[u64]) -> u64 {
+    count_stones(stones, 25)
 }
 
-fn part2(stones: &Vec<u64>, max_level: i32) -> u64 {
-    stones.iter()
-        .map(|&s| blink_stone(s, max_level, 0, &mut HashMap::new()))
-        .sum()
+fn part2(stones: &[u64], blinks: usize) -> u64 {
+    count_stones(stones, blinks)
 }
 
 fn parse_input(input: &str) -> Vec<u64> {
-    input.split_whitespace()
+    input
+        .split_whitespace()
         .map(|d| d.parse().unwrap())
         .collect()
 }
@@ -68,8 +389,20 @@ pub fn day11() {
     let stones = parse_input(&input);
 
     println!("Part 1: {}", part1(&stones));
-    println!("Part 1 (2): {}", part2(&stones, 25));
     println!("Part 2: {}", part2(&stones, 75));
+    println!(
+        "After 1000 blinks: {}",
+        count_stones::<BigUint>(&stones, 1000)
+    );
+
+    let graph = StoneGraph::new(&stones, &Rules::puzzle()).expect("The puzzle rules close");
+    let settling = graph.settle(&stones);
+    println!(
+        "Closed set: {} values, settling after {} blinks with period {}",
+        graph.len(),
+        settling.blink,
+        settling.period
+    );
 }
 
 #[cfg(test)]
@@ -82,4 +415,161 @@ mod tests {
         let stones = parse_input(&input);
         assert_eq!(55312, part1(&stones));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_histogram() {
+        let stones = parse_input("125 17");
+        let mut simulation: Simulation = Simulation::new(&stones, Rules::puzzle());
+        let stats = simulation.run(6).unwrap();
+
+        // 2097446912 14168 4048 2 0 2 4 40 48 2024 40 48 80 96 2 8 6 7 6 0 3 2
+        assert_eq!(22, stats[5].total);
+        assert_eq!(15, stats[5].distinct);
+        assert_eq!(
+            vec![3, 4, 5, 9, 13, 22],
+            stats.iter().map(|s| s.total).collect::<Vec<_>>()
+        );
+        assert_eq!(4, simulation.count(2));
+        assert_eq!(0, simulation.count(125));
+        assert_eq!(6, simulation.blinks());
+    }
+
+    #[test]
+    fn test_matches_memoized() {
+        let stones = parse_input("5 62914 65 972 0 805922 6521 1639064");
+        for blinks in [0, 1, 10, 30] {
+            let memoized: u64 = stones
+                .iter()
+                .map(|&s| blink_stone(s, blinks, 0, &mut HashMap::new()))
+                .sum();
+            assert_eq!(memoized, part2(&stones, blinks as usize));
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // Collatz steps for the numbers on the way down from 3, so 3 ends up cycling 4, 2, 1
+        // while 7, which no rule matches, never changes
+        let rules = Rules::new(vec![
+            Rule::Replace(1, 4),
+            Rule::Replace(2, 1),
+            Rule::Replace(4, 2),
+            Rule::Replace(3, 10),
+            Rule::Replace(10, 5),
+            Rule::Replace(5, 16),
+            Rule::Replace(16, 8),
+            Rule::Replace(8, 4),
+        ]);
+        let mut simulation: Simulation = Simulation::new(&[3, 3, 7], rules);
+        simulation.run(7).unwrap();
+        assert_eq!(3, simulation.total());
+        assert_eq!(2, simulation.distinct());
+        assert_eq!(2, simulation.count(1));
+        assert_eq!(1, simulation.count(7));
+
+        // Splitting doubles the stones without ever producing zeros
+        let doubling = Rules::new(vec![Rule::SplitEvenDigits, Rule::Multiply(11)]);
+        let mut simulation: Simulation = Simulation::new(&[1], doubling);
+        let stats = simulation.run(4).unwrap();
+        assert_eq!(
+            vec![1, 2, 2, 4],
+            stats.iter().map(|s| s.total).collect::<Vec<_>>()
+        );
+
+        // Tripling forever runs out of u64 and leaves the last stones that fit
+        let tripling = Rules::new(vec![Rule::Multiply(3)]);
+        let mut simulation: Simulation = Simulation::new(&[1], tripling);
+        assert_eq!(
+            Err(StoneOverflow {
+                stone: 3u64.pow(40),
+                factor: 3
+            }),
+            simulation.run(100)
+        );
+        assert_eq!(40, simulation.blinks());
+        assert_eq!(1, simulation.count(3u64.pow(40)));
+    }
+
+    #[test]
+    fn test_big_counts() {
+        let stones = parse_input("125 17");
+        let mut simulation: Simulation<BigUint> = Simulation::new(&stones, Rules::puzzle());
+        let stats = simulation.run(1000).unwrap();
+
+        assert_eq!(BigUint::from(part2(&stones, 75)), stats[74].total);
+        assert!(stats[999].total.bits() > 64);
+        // The set of distinct values stops growing long before 1000 blinks
+        assert_eq!(stats[499].distinct, stats[999].distinct);
+    }
+
+    #[test]
+    fn test_stone_graph() {
+        let stones = parse_input("125 17");
+        let graph = StoneGraph::new(&stones, &Rules::puzzle()).unwrap();
+        assert_eq!(76, graph.len());
+        assert!(graph.values().contains(&2024));
+
+        let settling = graph.settle(&stones);
+        let mut simulation: Simulation<BigUint> = Simulation::new(&stones, Rules::puzzle());
+        let stats = simulation.run(settling.blink).unwrap();
+        assert_eq!(
+            Settling {
+                blink: 17,
+                period: 1
+            },
+            settling
+        );
+        // Values only reachable on the way in from 125 and 17 never come back
+        assert_eq!(52, stats[15].distinct);
+        assert_eq!(54, stats[16].distinct);
+        assert_eq!(54, StoneGraph::new(&[0], &Rules::puzzle()).unwrap().len());
+
+        assert_eq!(BigUint::from(55312u32), graph.count_after(&stones, 25));
+        let mut simulation: Simulation<BigUint> = Simulation::new(&stones, Rules::puzzle());
+        simulation.run(200).unwrap();
+        assert_eq!(simulation.total(), graph.count_after(&stones, 200));
+    }
+
+    #[test]
+    fn test_open_rules() {
+        // Doubling never closes, and a bigger factor overflows before the limit is hit
+        let doubling = Rules::new(vec![Rule::Multiply(2)]);
+        assert_eq!(
+            Some(StoneGraphError::TooManyValues(50)),
+            StoneGraph::with_limit(&[1], &doubling, 50).err()
+        );
+        let growing = Rules::new(vec![Rule::Multiply(1 << 20)]);
+        assert_eq!(
+            Some(StoneGraphError::Overflow(StoneOverflow {
+                stone: 1 << 60,
+                factor: 1 << 20
+            })),
+            StoneGraph::new(&[1], &growing).err()
+        );
+    }
+
+    #[test]
+    fn test_count_mod() {
+        let stones = [0];
+        let graph = StoneGraph::new(&stones, &Rules::puzzle()).unwrap();
+        let modulus = BigUint::from(1_000_000_007u32);
+
+        // Deep enough that the exact count is thousands of bits and the modulus matters
+        let mut simulation: Simulation<BigUint> = Simulation::new(&stones, Rules::puzzle());
+        let stats = simulation.run(1000).unwrap();
+        for blinks in [0, 1, 75, 500, 1000] {
+            let stepped = match blinks {
+                0 => BigUint::from(1u8),
+                _ => stats[blinks - 1].total.clone(),
+            };
+            assert_eq!(
+                stepped % &modulus,
+                graph.count_after_mod(&stones, blinks as u64, &modulus)
+            );
+        }
+        assert_eq!(
+            graph.count_after(&stones, 1000) % &modulus,
+            graph.count_after_mod(&stones, 1000, &modulus)
+        );
+    }
+}
@@ -1,156 +1,376 @@
-﻿use crate::util::vec2::Vec2;
+use crate::util::vec2::Vec2;
+use num_bigint::BigInt;
+use num_integer::{ExtendedGcd, Integer};
 use regex::Regex;
-use std::collections::HashSet;
+use std::cmp::Ordering;
 use std::fs;
 
+#[derive(Clone, Debug)]
+pub struct Button {
+    name: String,
+    step: Vec2<i64>,
+    cost: i64,
+}
+
 pub struct Machine {
-    button_a: Vec2<i64>,
-    button_b: Vec2<i64>,
+    buttons: Vec<Button>,
     prize: Vec2<i64>,
 }
 
-fn input_to_vec2(input: &str) -> Vec2<i64> {
-    let input_regex = Regex::new(r"X.(\d+), Y.(\d+)").expect("Bad regex");
-    let (_, [x, y]) = input_regex.captures(&input).expect("Could not match regex").extract();
-    Vec2::new(x.parse().unwrap(), y.parse().unwrap())
+const A_COST: i64 = 3;
+const B_COST: i64 = 1;
+
+fn default_cost(name: &str) -> Option<i64> {
+    match name {
+        "A" => Some(A_COST),
+        "B" => Some(B_COST),
+        _ => None,
+    }
 }
 
+/// Parses blocks of `Button <name>: X+<x>, Y+<y>` lines followed by a `Prize` line. Any
+/// button may end in `, Cost: <n>`; A and B fall back to the puzzle's 3 and 1 tokens.
 pub fn parse_input(input: &str) -> Vec<Machine> {
-    let mut res = Vec::new();
-    let mut lines = input.lines();
+    let button_regex =
+        Regex::new(r"^Button (\w+): X\+(\d+), Y\+(\d+)(?:, Cost: (\d+))?$").expect("Bad regex");
+    let prize_regex = Regex::new(r"^Prize: X=(\d+), Y=(\d+)$").expect("Bad regex");
 
-    loop {
-        let a = lines.next();
-        if !a.is_some() {
-            return res;
+    let mut res = Vec::new();
+    let mut buttons = Vec::new();
+
+    for line in input.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(caps) = button_regex.captures(line) {
+            let name = caps[1].to_string();
+            let cost = match caps.get(4) {
+                Some(cost) => cost.as_str().parse().unwrap(),
+                None => default_cost(&name).expect("Button needs an explicit cost"),
+            };
+            buttons.push(Button {
+                step: Vec2::new(caps[2].parse().unwrap(), caps[3].parse().unwrap()),
+                name,
+                cost,
+            });
+        } else {
+            let (_, [x, y]) = prize_regex
+                .captures(line)
+                .expect("Could not match regex")
+                .extract();
+            res.push(Machine {
+                buttons: std::mem::take(&mut buttons),
+                prize: Vec2::new(x.parse().unwrap(), y.parse().unwrap()),
+            });
         }
-        let button_a = input_to_vec2(a.unwrap());
-        let button_b = input_to_vec2(lines.next().unwrap());
-        let prize = input_to_vec2(lines.next().unwrap());
-        lines.next();
-        res.push(Machine {
-            button_a,
-            button_b,
-            prize,
-        });
     }
+
+    res
 }
 
-const A_COST: i64 = 3;
-const B_COST: i64 = 1;
+/// Cheapest non-negative `(a, b)` with `a * ca + b * cb == target`, or None if there is
+/// no such pair. All integer solutions are `(a0 + k * cb / g, b0 - k * ca / g)` where
+/// `(a0, b0)` comes from the extended gcd, and since the cost is linear in `k` the
+/// cheapest one sits at an end of the range of `k` that keeps both counts non-negative.
+fn solve_line(
+    ca: &BigInt,
+    cb: &BigInt,
+    target: &BigInt,
+    costs: (i64, i64),
+) -> Option<(BigInt, BigInt)> {
+    let zero = BigInt::default();
+    let (a_cost, b_cost) = (BigInt::from(costs.0), BigInt::from(costs.1));
+
+    if *ca == zero && *cb == zero {
+        return (*target == zero).then(|| (zero.clone(), zero.clone()));
+    }
 
-fn solve_machine(machine: &Machine) -> Option<i64> {
-    let mut heads = vec![(machine.prize, 0, 0)];
-    let mut cheapest = None;
-    let max = Vec2::new(i64::MAX, i64::MAX);
-    let mut visited = HashSet::new();
-
-    while !heads.is_empty() {
-        let (prize, num_a, num_b) = heads.pop().unwrap();
-        let cost = num_a * A_COST + num_b * B_COST;
-        if !visited.insert(prize) {
-            continue;
-        }
-        if !prize.in_bounds(Vec2::zero(), max) || cost > cheapest.unwrap_or(i64::MAX) {
-            continue;
-        }
+    let ExtendedGcd { gcd, x, y } = ca.extended_gcd(cb);
+    if target % &gcd != zero {
+        return None;
+    }
 
-        if prize == Vec2::zero() {
-            cheapest = Some(cost);
-            continue;
+    let scale = target / &gcd;
+    let (a0, b0) = (x * &scale, y * &scale);
+    let (step_a, step_b) = (cb / &gcd, -(ca / &gcd));
+
+    // Range of k that keeps a0 + k * step_a and b0 + k * step_b non-negative
+    let mut low: Option<BigInt> = None;
+    let mut high: Option<BigInt> = None;
+    for (start, step) in [(&a0, &step_a), (&b0, &step_b)] {
+        if step > &zero {
+            let bound = (-start).div_ceil(step);
+            low = Some(low.map_or(bound.clone(), |l| l.max(bound)));
+        } else if step < &zero {
+            let bound = (-start).div_floor(step);
+            high = Some(high.map_or(bound.clone(), |h| h.min(bound)));
+        } else if start < &zero {
+            return None;
         }
-
-        let a = (prize - machine.button_a, num_a + 1, num_b);
-        let b = (prize - machine.button_b, num_a, num_b + 1);
-
-        heads.push(a);
-        heads.push(b);
     }
-    cheapest
-}
-
-fn get_primes(max: i64) -> Vec<i64> {
-    let mut primes = vec![2];
 
-    for i in 3..max {
-        if !primes.iter().any(|p| i % p == 0) {
-            primes.push(i);
+    if let (Some(low), Some(high)) = (&low, &high) {
+        if low > high {
+            return None;
         }
     }
 
-    primes
-}
+    let slope = &a_cost * &step_a + &b_cost * &step_b;
+    let k = match slope.cmp(&zero) {
+        Ordering::Greater => low?,
+        Ordering::Less => high?,
+        Ordering::Equal => low.or(high).unwrap_or_default(),
+    };
 
-fn get_prime_factors(num: i64, primes: &[i64]) -> Vec<i64> {
-    let mut factors = Vec::new();
+    Some((a0 + &k * step_a, b0 + k * step_b))
+}
 
-    for &p in primes {
-        let mut pn = num;
-        while pn % p == 0 {
-            factors.push(p);
-            pn = pn / p;
+/// Exact cheapest presses of two buttons that reach `prize`. Independent buttons have
+/// exactly one solution, found with Cramer's rule. Collinear buttons have either none or
+/// a whole line of them, which `solve_line` searches along one axis.
+fn solve_pair(a: &Button, b: &Button, prize: Vec2<i64>) -> Option<(i64, i64)> {
+    let big = |v: i64| BigInt::from(v);
+    let (ax, ay) = (big(a.step.x), big(a.step.y));
+    let (bx, by) = (big(b.step.x), big(b.step.y));
+    let (px, py) = (big(prize.x), big(prize.y));
+    let zero = BigInt::default();
+
+    let det = &ax * &by - &bx * &ay;
+
+    let (a, b) = if det != zero {
+        let a = &px * &by - &bx * &py;
+        let b = &ax * &py - &px * &ay;
+        if &a % &det != zero || &b % &det != zero {
+            return None;
         }
+        (a / &det, b / &det)
+    } else {
+        let costs = (a.cost, b.cost);
+        let (a, b) = if ax != zero || bx != zero {
+            solve_line(&ax, &bx, &px, costs)?
+        } else {
+            solve_line(&ay, &by, &py, costs)?
+        };
+        if &a * &ax + &b * &bx != px || &a * &ay + &b * &by != py {
+            return None;
+        }
+        (a, b)
+    };
+
+    if a < zero || b < zero {
+        return None;
     }
 
-    factors
+    Some((i64::try_from(a).ok()?, i64::try_from(b).ok()?))
 }
 
-fn to_vec_float64(v: &Vec2<i64>) -> Vec2<f64> {
-    Vec2::new(v.x as f64, v.y as f64)
+/// Most presses of `button` that fit inside `prize`, or None if the button does not move
+/// the claw and so never needs pressing.
+fn press_bound(button: &Button, prize: Vec2<i64>) -> Option<i64> {
+    [(button.step.x, prize.x), (button.step.y, prize.y)]
+        .into_iter()
+        .filter(|&(step, _)| step > 0)
+        .map(|(step, target)| target / step)
+        .min()
 }
 
-fn solve_machine_v2(machine: &Machine) -> Option<i64> {
-    let p = to_vec_float64(&machine.prize);
-    let a = to_vec_float64(&machine.button_a);
-    let b = to_vec_float64(&machine.button_b);
-    let B = (a.y * p.x - a.x * p.y) / (a.y * b.x - a.x * b.y);
-    let A = (p.y - B * b.y) / a.y;
+/// Cheapest press counts, one per button, that land exactly on the prize. The two buttons
+/// with the most room are solved exactly by `solve_pair`, every other button is tried at
+/// each count up to its bound, cutting a branch once it costs more than the best so far.
+/// The last of those only needs the two ends from `end_counts`.
+pub fn cheapest_presses(machine: &Machine) -> Option<Vec<i64>> {
+    let mut order: Vec<(usize, i64)> = machine
+        .buttons
+        .iter()
+        .enumerate()
+        .filter_map(|(i, button)| Some((i, press_bound(button, machine.prize)?)))
+        .collect();
+    // Fewest presses to try first, so the two widest ranges are left to `solve_pair`
+    order.sort_by_key(|&(i, bound)| (bound, i));
+
+    let order: Vec<usize> = order.into_iter().map(|(i, _)| i).collect();
+    let split = order.len().saturating_sub(2);
+    let (searched, exact) = order.split_at(split);
+
+    let mut presses = vec![0; machine.buttons.len()];
+    let mut best = None;
+    search(
+        machine,
+        searched,
+        exact,
+        machine.prize,
+        0,
+        &mut presses,
+        &mut best,
+    );
+    best.map(|(_, presses)| presses)
+}
 
-    let ib = B as i64;
-    let ia = A as i64;
+fn search(
+    machine: &Machine,
+    searched: &[usize],
+    exact: &[usize],
+    remaining: Vec2<i64>,
+    cost: i64,
+    presses: &mut Vec<i64>,
+    best: &mut Option<(i64, Vec<i64>)>,
+) {
+    if best
+        .as_ref()
+        .is_some_and(|(best_cost, _)| cost >= *best_cost)
+    {
+        return;
+    }
 
-    if machine.button_a * ia + machine.button_b * ib == machine.prize {
-        Some(ia * A_COST + ib * B_COST)
-    } else {
-        None
+    let Some((&next, rest)) = searched.split_first() else {
+        let buttons = &machine.buttons;
+        let solved = match *exact {
+            [] => (remaining == Vec2::new(0, 0)).then_some(vec![]),
+            [a] => {
+                let step = buttons[a].step;
+                let count = press_bound(&buttons[a], remaining).unwrap_or(0);
+                (step * count == remaining).then(|| vec![(a, count)])
+            }
+            [a, b] => solve_pair(&buttons[a], &buttons[b], remaining)
+                .map(|(ca, cb)| vec![(a, ca), (b, cb)]),
+            _ => unreachable!(),
+        };
+        let Some(solved) = solved else { return };
+
+        let total = cost
+            + solved
+                .iter()
+                .map(|&(i, count)| count * buttons[i].cost)
+                .sum::<i64>();
+        if best
+            .as_ref()
+            .is_none_or(|(best_cost, _)| total < *best_cost)
+        {
+            let mut found = presses.clone();
+            for (i, count) in solved {
+                found[i] = count;
+            }
+            *best = Some((total, found));
+        }
+        return;
+    };
+
+    let button = &machine.buttons[next];
+    let bound = press_bound(button, remaining).unwrap_or(0);
+    let ends = match (rest, exact) {
+        ([], &[a, b]) => {
+            let (a, b) = (&machine.buttons[a], &machine.buttons[b]);
+            end_counts(button, a, b, remaining, bound)
+        }
+        _ => None,
+    };
+    let counts: Box<dyn Iterator<Item = i64>> = match ends {
+        Some(ends) => Box::new(ends.into_iter()),
+        None => Box::new(0..=bound),
+    };
+
+    for count in counts {
+        let cost = cost + count * button.cost;
+        // More presses only cost more
+        if best
+            .as_ref()
+            .is_some_and(|(best_cost, _)| cost >= *best_cost)
+        {
+            break;
+        }
+        presses[next] = count;
+        search(
+            machine,
+            rest,
+            exact,
+            remaining - button.step * count,
+            cost,
+            presses,
+            best,
+        );
     }
+    presses[next] = 0;
 }
 
-/*
-Button A: X+94, Y+34
-Button B: X+22, Y+67
-Prize: X=10000000008400, Y=10000000005400
+/// Fewest and most presses of `button`, up to `bound`, that leave a remainder the
+/// independent pair `a`, `b` covers with whole, non-negative presses. The pair's presses
+/// are linear in the count, so the total cost is too and the cheapest count is one of
+/// these two. None if `a` and `b` are collinear and the counts have to be tried one by one.
+fn end_counts(
+    button: &Button,
+    a: &Button,
+    b: &Button,
+    remaining: Vec2<i64>,
+    bound: i64,
+) -> Option<Vec<i64>> {
+    let wide = |v: i64| v as i128;
+    let (s, a, b, r) = (button.step, a.step, b.step, remaining);
+    let mut det = wide(a.x) * wide(b.y) - wide(b.x) * wide(a.y);
+    if det == 0 {
+        return None;
+    }
 
+    // Presses of a and b are (n - count * k) / det for each of these
+    let mut lines = [
+        (
+            wide(r.x) * wide(b.y) - wide(b.x) * wide(r.y),
+            wide(s.x) * wide(b.y) - wide(b.x) * wide(s.y),
+        ),
+        (
+            wide(a.x) * wide(r.y) - wide(r.x) * wide(a.y),
+            wide(a.x) * wide(s.y) - wide(s.x) * wide(a.y),
+        ),
+    ];
+    if det < 0 {
+        det = -det;
+        for (n, k) in &mut lines {
+            (*n, *k) = (-*n, -*k);
+        }
+    }
 
+    let (mut low, mut high) = (0i128, wide(bound));
+    let mut period = 1i128;
+    for &(n, k) in &lines {
+        match k.cmp(&0) {
+            Ordering::Greater => high = high.min(Integer::div_floor(&n, &k)),
+            Ordering::Less => low = low.max(Integer::div_ceil(&n, &k)),
+            Ordering::Equal if n < 0 => return Some(vec![]),
+            Ordering::Equal => {}
+        }
+        period = period.lcm(&(det / det.gcd(&k)));
+    }
 
-A ax + B bx = Px
-A ay + B by = Py
-A = (Py - B by) / ay
+    // Whole presses repeat every `period` counts, so the first one is within a period
+    let whole = |count: i128| lines.iter().all(|&(n, k)| (n - count * k) % det == 0);
+    let Some(first) = (low..=high.min(low + period - 1)).find(|&c| whole(c)) else {
+        return Some(vec![]);
+    };
+    let last = first + (high - first) / period * period;
 
-ax (Py - B by) / ay + B bx = Px
-B bx = Px - ax Py / ay + ax B by / ay
-ay B bx = ay Px - ax Py + ax B by
-ay B bx - ax B by = ay Px - ax Py
-B (ay bx - ax by) = (ay Px - ax Py)
-B = (ay Px - ax Py) / (ay bx - ax by)
+    Some(vec![first as i64, last as i64])
+}
 
- */
+/// Minimum number of tokens to win the prize, if it can be won at all.
+fn solve_machine(machine: &Machine) -> Option<i64> {
+    let presses = cheapest_presses(machine)?;
+    Some(
+        presses
+            .iter()
+            .zip(&machine.buttons)
+            .map(|(count, button)| count * button.cost)
+            .sum(),
+    )
+}
 
 pub fn part1(machines: &[Machine]) -> i64 {
-    machines.iter()
-        .filter_map(|m| solve_machine(m))
-        .sum()
+    machines.iter().filter_map(solve_machine).sum()
 }
 
-
 pub fn part2(machines: &[Machine], offset: i64) -> i64 {
-    machines.iter()
+    machines
+        .iter()
         .map(|m| Machine {
-            button_a: m.button_a,
-            button_b: m.button_b,
+            buttons: m.buttons.clone(),
             prize: m.prize + Vec2::new(offset, offset),
         })
-        .filter_map(|m| solve_machine_v2(&m))
+        .filter_map(|m| solve_machine(&m))
         .sum()
 }
 pub fn day13() {
@@ -164,6 +384,14 @@ pub fn day13() {
 mod tests {
     use super::*;
 
+    fn button(name: &str, step: (i64, i64), cost: i64) -> Button {
+        Button {
+            name: name.to_string(),
+            step: Vec2::new(step.0, step.1),
+            cost,
+        }
+    }
+
     #[test]
     pub fn test_p1() {
         let input = "Button A: X+94, Y+34
@@ -212,4 +440,187 @@ Prize: X=18641, Y=10279";
         let machines = parse_input(&input);
         assert_eq!(36954, part2(&machines, 0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_collinear() {
+        let machine = |a: (i64, i64), b: (i64, i64), p: (i64, i64)| Machine {
+            buttons: vec![button("A", a, A_COST), button("B", b, B_COST)],
+            prize: Vec2::new(p.0, p.1),
+        };
+
+        // 7 * 2 + 3 * 2 = 20 is the only way to reach the prize
+        assert_eq!(
+            Some(2 * A_COST + 2 * B_COST),
+            solve_machine(&machine((7, 14), (3, 6), (20, 40)))
+        );
+        // A covers four times the distance of B for three times the cost
+        assert_eq!(
+            Some(3 * A_COST),
+            solve_machine(&machine((4, 4), (1, 1), (12, 12)))
+        );
+        // Off the line of the buttons
+        assert_eq!(None, solve_machine(&machine((4, 4), (1, 1), (12, 13))));
+        // Odd target with even buttons
+        assert_eq!(None, solve_machine(&machine((2, 0), (4, 0), (7, 0))));
+        assert_eq!(Some(0), solve_machine(&machine((0, 0), (0, 0), (0, 0))));
+    }
+
+    #[test]
+    pub fn test_large_offset() {
+        let input = "Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+
+Button A: X+17, Y+86
+Button B: X+84, Y+37
+Prize: X=7870, Y=6450
+
+Button A: X+69, Y+23
+Button B: X+27, Y+71
+Prize: X=18641, Y=10279";
+        let machines = parse_input(input);
+        assert_eq!(875318608908, part2(&machines, 10000000000000));
+    }
+
+    #[test]
+    pub fn test_costs() {
+        let input = "Button A: X+94, Y+34, Cost: 1
+Button B: X+22, Y+67, Cost: 3
+Prize: X=8400, Y=5400
+
+Button A: X+2, Y+2
+Button B: X+1, Y+1
+Button C: X+4, Y+4, Cost: 1
+Prize: X=12, Y=12";
+        let machines = parse_input(input);
+        assert_eq!(3, machines[1].buttons.len());
+        assert_eq!("C", machines[1].buttons[2].name);
+        // Same presses as the default prices, 80 A and 40 B
+        assert_eq!(Some(80 + 40 * 3), solve_machine(&machines[0]));
+        // Three presses of C beat any mix of A and B
+        assert_eq!(Some(vec![0, 0, 3]), cheapest_presses(&machines[1]));
+        assert_eq!(3, part1(&machines[1..]));
+    }
+
+    #[test]
+    pub fn test_many_buttons() {
+        let machine = Machine {
+            buttons: vec![
+                button("A", (3, 1), 2),
+                button("B", (1, 3), 2),
+                button("C", (1, 1), 1),
+                button("D", (0, 0), 0),
+                button("E", (5, 0), 1),
+            ],
+            prize: Vec2::new(23, 9),
+        };
+
+        // Brute force over every combination of the four moving buttons
+        let mut expected = None;
+        for a in 0..=8 {
+            for b in 0..=3 {
+                for c in 0..=9 {
+                    for e in 0..=4 {
+                        let x = 3 * a + b + c + 5 * e;
+                        let y = a + 3 * b + c;
+                        if (x, y) == (23, 9) {
+                            let cost = 2 * a + 2 * b + c + e;
+                            expected = Some(expected.map_or(cost, |best: i64| best.min(cost)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let presses = cheapest_presses(&machine).unwrap();
+        let reached = presses
+            .iter()
+            .zip(&machine.buttons)
+            .fold(Vec2::new(0, 0), |pos, (&count, button)| {
+                pos + button.step * count
+            });
+        assert_eq!(machine.prize, reached);
+        assert_eq!(0, presses[3]);
+        assert_eq!(expected, solve_machine(&machine));
+
+        let single = Machine {
+            buttons: vec![button("A", (2, 3), 5)],
+            prize: Vec2::new(8, 12),
+        };
+        assert_eq!(Some(20), solve_machine(&single));
+        let unreachable = Machine {
+            buttons: vec![button("A", (2, 3), 5)],
+            prize: Vec2::new(8, 13),
+        };
+        assert_eq!(None, solve_machine(&unreachable));
+    }
+
+    #[test]
+    pub fn test_three_buttons() {
+        let three = |prize: (i64, i64)| Machine {
+            buttons: vec![
+                button("A", (94, 34), 3),
+                button("B", (22, 67), 1),
+                button("C", (17, 86), 2),
+            ],
+            prize: Vec2::new(prize.0, prize.1),
+        };
+
+        // Every count of C against a brute force over all three buttons
+        for (x, y) in [
+            (8400, 5400),
+            (1000, 2000),
+            (1710, 2586),
+            (999, 1001),
+            (0, 0),
+        ] {
+            let mut expected = None;
+            for c in 0..=x.min(y) / 17 {
+                for a in 0..=(x - 17 * c) / 94 {
+                    let (rx, ry) = (x - 17 * c - 94 * a, y - 86 * c - 34 * a);
+                    if ry >= 0 && rx % 22 == 0 && rx / 22 * 67 == ry {
+                        let cost = 3 * a + rx / 22 + 2 * c;
+                        expected = Some(expected.map_or(cost, |best: i64| best.min(cost)));
+                    }
+                }
+            }
+            assert_eq!(expected, solve_machine(&three((x, y))));
+        }
+
+        // Hundreds of billions of presses of each button are possible at the part 2 offset
+        let offset = 10000000000000;
+        let machine = three((8400 + offset, 5400 + offset));
+        let presses = cheapest_presses(&machine).unwrap();
+        let reached = presses
+            .iter()
+            .zip(&machine.buttons)
+            .fold(Vec2::new(0, 0), |pos, (&count, button)| {
+                pos + button.step * count
+            });
+        assert_eq!(machine.prize, reached);
+        let two = Machine {
+            buttons: machine.buttons[..2].to_vec(),
+            prize: machine.prize,
+        };
+        assert!(solve_machine(&machine) <= solve_machine(&two).or(Some(i64::MAX)));
+    }
+
+    #[test]
+    pub fn test_search_order() {
+        // C has the narrowest range, so only its 4 counts are tried while A and B, with
+        // hundreds of millions of possible presses each, are solved exactly
+        let machine = Machine {
+            buttons: vec![
+                button("A", (1, 0), 1),
+                button("B", (0, 1), 1),
+                button("C", (100_000_000, 100_000_000), 1),
+            ],
+            prize: Vec2::new(300_000_005, 300_000_007),
+        };
+        assert_eq!(Some(vec![5, 7, 3]), cheapest_presses(&machine));
+    }
+}
@@ -1,9 +1,10 @@
 ﻿use crate::util::vec2::Vec2;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::fs;
 
-enum Input {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
     Up,
     Down,
     Left,
@@ -16,7 +17,7 @@ impl Input {
             Input::Up => Vec2::new(0, -1),
             Input::Down => Vec2::new(0, 1),
             Input::Left => Vec2::new(-1, 0),
-            Input::Right => Vec2::new(1, 0)
+            Input::Right => Vec2::new(1, 0),
         }
     }
 }
@@ -30,159 +31,419 @@ impl TryFrom<char> for Input {
             '>' => Ok(Self::Right),
             '^' => Ok(Self::Up),
             'v' => Ok(Self::Down),
-            _ => Err(())
+            _ => Err(()),
         }
     }
 }
 
-type Inputs = Vec<Input>;
+pub type Inputs = Vec<Input>;
 
-#[derive(Clone, PartialEq)]
-enum Obj {
-    Robot,
-    Box,
-    BoxLeft,
-    BoxRight,
-    Wall,
+/// A rigid box covering one or more cells. Every cell moves together, so a push that
+/// reaches any of them has to move all of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub cells: Vec<Vec2<i32>>,
 }
 
-impl Obj {
-    fn new(c: char) -> Option<Self> {
-        match c {
-            '#' => Some(Self::Wall),
-            'O' => Some(Self::Box),
-            '@' => Some(Self::Robot),
-            _ => None,
+impl Block {
+    /// The top-left cell, which is the one GPS coordinates are measured from
+    fn anchor(&self) -> Vec2<i32> {
+        *self
+            .cells
+            .iter()
+            .min_by_key(|c| (c.y, c.x))
+            .expect("Block has no cells")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Warehouse {
+    walls: HashSet<Vec2<i32>>,
+    robot: Vec2<i32>,
+    blocks: Vec<Block>,
+    /// Id of the block on each occupied cell
+    occupied: HashMap<Vec2<i32>, usize>,
+}
+
+impl Warehouse {
+    pub fn new(walls: HashSet<Vec2<i32>>, robot: Vec2<i32>, blocks: Vec<Block>) -> Warehouse {
+        let mut occupied = HashMap::new();
+        for (id, block) in blocks.iter().enumerate() {
+            for &cell in &block.cells {
+                assert!(
+                    !walls.contains(&cell) && cell != robot,
+                    "Block {id} overlaps something at {cell}"
+                );
+                assert!(
+                    occupied.insert(cell, id).is_none(),
+                    "Blocks overlap at {cell}"
+                );
+            }
         }
+        Warehouse {
+            walls,
+            robot,
+            blocks,
+            occupied,
+        }
+    }
+
+    /// Reads `#` walls and the `@` robot. Each `O` is a box of its own and each `[]` pair
+    /// is a two cell box. Any other letter marks the cells of a box of any shape, with
+    /// touching cells of the same letter belonging to the same box.
+    pub fn parse(diagram: &str) -> Warehouse {
+        let grid: HashMap<Vec2<i32>, char> = diagram
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.trim()
+                    .chars()
+                    .enumerate()
+                    .map(move |(x, c)| (Vec2::new(x as i32, y as i32), c))
+            })
+            .collect();
+        let mut cells: Vec<Vec2<i32>> = grid.keys().copied().collect();
+        cells.sort_by_key(|c| (c.y, c.x));
+
+        let mut walls = HashSet::new();
+        let mut robot = None;
+        let mut blocks = Vec::new();
+        let mut claimed = HashSet::new();
+
+        for cell in cells {
+            match grid[&cell] {
+                '#' => {
+                    walls.insert(cell);
+                }
+                '@' => robot = Some(cell),
+                'O' => blocks.push(Block { cells: vec![cell] }),
+                '[' => {
+                    let right = cell + Vec2::new(1, 0);
+                    assert_eq!(Some(&']'), grid.get(&right), "Unpaired `[` at {cell}");
+                    claimed.insert(right);
+                    blocks.push(Block {
+                        cells: vec![cell, right],
+                    });
+                }
+                ']' => assert!(claimed.contains(&cell), "Unpaired `]` at {cell}"),
+                c if c.is_ascii_alphabetic() && !claimed.contains(&cell) => {
+                    let mut shape = vec![cell];
+                    claimed.insert(cell);
+                    let mut i = 0;
+                    while i < shape.len() {
+                        for dir in Vec2::all_dirs() {
+                            let next = shape[i] + dir;
+                            if grid.get(&next) == Some(&c) && claimed.insert(next) {
+                                shape.push(next);
+                            }
+                        }
+                        i += 1;
+                    }
+                    blocks.push(Block { cells: shape });
+                }
+                _ => {}
+            }
+        }
+
+        Warehouse::new(walls, robot.expect("Could not find robot"), blocks)
+    }
+
+    pub fn robot(&self) -> Vec2<i32> {
+        self.robot
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Ids of every block the robot would shove by stepping in `dir`, or None if any of
+    /// them would hit a wall. Nothing is changed.
+    fn dependencies(&self, dir: Vec2<i32>) -> Option<Vec<usize>> {
+        let mut moved = Vec::new();
+        let mut found = HashSet::new();
+        let mut stack = vec![self.robot + dir];
+
+        while let Some(cell) = stack.pop() {
+            if self.walls.contains(&cell) {
+                return None;
+            }
+            let Some(&id) = self.occupied.get(&cell) else {
+                continue;
+            };
+            if found.insert(id) {
+                moved.push(id);
+                for &block_cell in &self.blocks[id].cells {
+                    let next = block_cell + dir;
+                    if self.occupied.get(&next) != Some(&id) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        Some(moved)
+    }
+
+    /// Moves the robot one step in `dir`, pushing every block in the way. Returns the ids
+    /// of the pushed blocks, or None if the robot could not move, in which case nothing
+    /// has changed.
+    pub fn push(&mut self, dir: Vec2<i32>) -> Option<Vec<usize>> {
+        let moved = self.dependencies(dir)?;
+        self.shift(&moved, dir);
+        Some(moved)
+    }
+
+    /// Moves the robot and the given blocks one step in `dir` without checking whether
+    /// anything is in the way.
+    fn shift(&mut self, ids: &[usize], dir: Vec2<i32>) {
+        // Lift every moved block before putting any back down, so they can't overwrite
+        // each other's cells
+        for &id in ids {
+            for cell in &self.blocks[id].cells {
+                self.occupied.remove(cell);
+            }
+        }
+        for &id in ids {
+            for cell in &mut self.blocks[id].cells {
+                *cell = *cell + dir;
+                self.occupied.insert(*cell, id);
+            }
+        }
+        self.robot = self.robot + dir;
+    }
+
+    /// Checks that the robot and every block sit on their own cells, clear of the walls,
+    /// and that the cell index agrees with the blocks.
+    pub fn check(&self) -> Result<(), String> {
+        if self.walls.contains(&self.robot) {
+            return Err(format!("Robot is inside a wall at {}", self.robot));
+        }
+
+        let mut cells = 0;
+        for (id, block) in self.blocks.iter().enumerate() {
+            for cell in &block.cells {
+                if self.walls.contains(cell) {
+                    return Err(format!("Block {id} is inside a wall at {cell}"));
+                }
+                if *cell == self.robot {
+                    return Err(format!("Block {id} is under the robot at {cell}"));
+                }
+                if self.occupied.get(cell) != Some(&id) {
+                    return Err(format!(
+                        "Cell {cell} of block {id} is not indexed as block {id}"
+                    ));
+                }
+            }
+            cells += block.cells.len();
+        }
+
+        if cells != self.occupied.len() {
+            return Err(format!(
+                "{} cells are indexed but blocks cover {cells}",
+                self.occupied.len()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn gps_sum(&self) -> i32 {
+        self.blocks
+            .iter()
+            .map(|block| gps_coord(block.anchor()))
+            .sum()
     }
 }
 
-impl Display for Obj {
+impl Display for Warehouse {
+    /// Boxes of one cell show as `O` and two cell wide ones as `[]`. Anything bigger gets
+    /// a letter from its id.
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Obj::Robot => write!(f, "@"),
-            Obj::Box => write!(f, "O"),
-            Obj::BoxLeft => write!(f, "["),
-            Obj::BoxRight => write!(f, "]"),
-            Obj::Wall => write!(f, "#"),
+        let size = self
+            .walls
+            .iter()
+            .chain(self.occupied.keys())
+            .fold(self.robot, |size, &cell| size.max(cell));
+
+        for y in 0..=size.y {
+            for x in 0..=size.x {
+                let pos = Vec2::new(x, y);
+                let c = if self.walls.contains(&pos) {
+                    '#'
+                } else if pos == self.robot {
+                    '@'
+                } else if let Some(&id) = self.occupied.get(&pos) {
+                    let block = &self.blocks[id];
+                    let anchor = block.anchor();
+                    match block.cells.len() {
+                        1 => 'O',
+                        2 if block.cells.contains(&(anchor + Vec2::new(1, 0))) => {
+                            if pos == anchor {
+                                '['
+                            } else {
+                                ']'
+                            }
+                        }
+                        _ => (b'a' + (id % 26) as u8) as char,
+                    }
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
         }
+        Ok(())
     }
 }
-type Map = HashMap<Vec2<i32>, Obj>;
 
-fn move_obj(map: &mut Map, from: Vec2<i32>, to: Vec2<i32>) {
-    let obj = map.remove(&from).expect("Can't move nothing!");
-    map.insert(to, obj);
+/// One entry of the move log: which way the robot tried to go and the blocks it pushed,
+/// or None if it was stuck.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveRecord {
+    pub input: Input,
+    pub moved: Option<Vec<usize>>,
 }
 
-fn parse_input(input: &str) -> (Map, Inputs) {
-    let (map_input, move_input) = input.split_once("\n\n").expect("Could not split input in two");
+/// Runs moves on a warehouse, logging each one so they can be undone and redone, and
+/// checking after every move that nothing has come apart.
+pub struct Simulator {
+    map: Warehouse,
+    /// Cells of each block relative to its first cell, which pushes must never change
+    shapes: Vec<Vec<Vec2<i32>>>,
+    log: Vec<MoveRecord>,
+    undone: Vec<MoveRecord>,
+}
 
-    let map = map_input.lines()
-        .enumerate()
-        .flat_map(|(y, line)| line.chars()
-            .enumerate()
-            .map(move |(x, c)| (Vec2::new(x as i32, y as i32), Obj::new(c))))
-        .filter_map(|(p, e)| match e {
-            Some(obj) => Some((p, obj)),
-            None => None,
-        });
+impl Simulator {
+    pub fn new(map: Warehouse) -> Simulator {
+        let shapes = map.blocks.iter().map(Simulator::shape).collect();
+        Simulator {
+            map,
+            shapes,
+            log: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
 
-    let map = Map::from_iter(map);
+    fn shape(block: &Block) -> Vec<Vec2<i32>> {
+        block
+            .cells
+            .iter()
+            .map(|&cell| cell - block.cells[0])
+            .collect()
+    }
 
-    let moves: Inputs = move_input.chars().filter_map(|c| c.try_into().ok()).collect();
-    (map, moves)
-}
+    pub fn map(&self) -> &Warehouse {
+        &self.map
+    }
 
-fn widen_pos(pos: Vec2<i32>) -> Vec2<i32> {
-    Vec2::new(pos.x * 2, pos.y)
-}
+    pub fn log(&self) -> &[MoveRecord] {
+        &self.log
+    }
 
-fn widen_map(map: &Map) -> Map {
-    let right = Vec2::new(1, 0);
-    Map::from_iter(map.iter()
-        .flat_map(move |(&p, o)| match o {
-            Obj::Robot => vec![(widen_pos(p), o.clone())],
-            Obj::Wall => vec![(widen_pos(p), o.clone()), (widen_pos(p) + right, o.clone())],
-            Obj::Box => vec![(widen_pos(p), Obj::BoxLeft), (widen_pos(p) + right, Obj::BoxRight)],
-            _ => vec![]
-        })
-    )
-}
+    /// Makes a move, dropping anything that was undone before it.
+    pub fn step(&mut self, input: Input) -> Result<&MoveRecord, String> {
+        let moved = self.map.push(input.get_move());
+        self.log.push(MoveRecord { input, moved });
+        self.undone.clear();
+        self.check()
+            .map_err(|e| format!("After move {}: {e}", self.log.len()))?;
+        Ok(self.log.last().unwrap())
+    }
 
-fn move_object(pos: Vec2<i32>, dir: Vec2<i32>, map: &mut Map) -> bool {
-    let target_pos = pos + dir;
-    let at_pos = map.get(&target_pos);
-    let did_move = match at_pos {
-        None => {
-            true
+    pub fn run(&mut self, inputs: &Inputs) -> Result<(), String> {
+        for &input in inputs {
+            self.step(input)?;
         }
-        Some(Obj::Box) => {
-            if move_object(target_pos, dir, map) {
-                true
-            } else {
-                false
-            }
+        Ok(())
+    }
+
+    /// Takes back the last move, returning it.
+    pub fn undo(&mut self) -> Option<&MoveRecord> {
+        let record = self.log.pop()?;
+        if let Some(moved) = &record.moved {
+            self.map
+                .shift(moved, Vec2::zero() - record.input.get_move());
         }
-        Some(Obj::BoxLeft) =>
-            if dir.x == 1 {
-                move_object(target_pos + Vec2::new(1, 0), dir, map) && move_object(target_pos, dir, map)
-            } else {
-                move_object(target_pos, dir, map) && move_object(target_pos + Vec2::new(1, 0), dir, map)
-            },
-        Some(Obj::BoxRight) =>
-            if dir.x == -1 {
-                move_object(target_pos + Vec2::new(-1, 0), dir, map) && move_object(target_pos, dir, map)
-            } else {
-                move_object(target_pos, dir, map) && move_object(target_pos + Vec2::new(-1, 0), dir, map)
-            },
-        _ => false
-    };
+        self.undone.push(record);
+        self.undone.last()
+    }
+
+    /// Makes the last undone move again, returning it.
+    pub fn redo(&mut self) -> Option<&MoveRecord> {
+        let record = self.undone.pop()?;
+        if let Some(moved) = &record.moved {
+            self.map.shift(moved, record.input.get_move());
+        }
+        self.log.push(record);
+        self.log.last()
+    }
 
-    if did_move {
-        move_obj(map, pos, target_pos);
+    /// Undoes or redoes moves until exactly `moves` of them have been made.
+    pub fn seek(&mut self, moves: usize) {
+        while self.log.len() > moves && self.undo().is_some() {}
+        while self.log.len() < moves && self.redo().is_some() {}
     }
-    did_move
+
+    pub fn check(&self) -> Result<(), String> {
+        self.map.check()?;
+        for (id, (block, shape)) in self.map.blocks.iter().zip(&self.shapes).enumerate() {
+            if Simulator::shape(block) != *shape {
+                return Err(format!("Block {id} has been pulled out of shape"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_input(input: &str) -> (Warehouse, Inputs) {
+    let (map_input, move_input) = input
+        .split_once("\n\n")
+        .expect("Could not split input in two");
+
+    let moves: Inputs = move_input
+        .chars()
+        .filter_map(|c| c.try_into().ok())
+        .collect();
+    (Warehouse::parse(map_input), moves)
+}
+
+/// Stretches the warehouse `scale` times wider. Walls and boxes cover `scale` cells for
+/// each cell they covered before, while the robot stays one cell wide.
+fn widen_map(map: &Warehouse, scale: i32) -> Warehouse {
+    let widen = |cell: Vec2<i32>| (0..scale).map(move |dx| Vec2::new(cell.x * scale + dx, cell.y));
+
+    Warehouse::new(
+        map.walls.iter().flat_map(|&wall| widen(wall)).collect(),
+        Vec2::new(map.robot.x * scale, map.robot.y),
+        map.blocks
+            .iter()
+            .map(|block| Block {
+                cells: block.cells.iter().flat_map(|&cell| widen(cell)).collect(),
+            })
+            .collect(),
+    )
 }
 
 fn gps_coord(pos: Vec2<i32>) -> i32 {
     pos.x + 100 * pos.y
 }
 
-fn part1(map: &Map, inputs: &Inputs) -> i32 {
+fn run(map: &Warehouse, inputs: &Inputs) -> Warehouse {
     let mut map = map.clone();
-    let mut robot_pos = *map.iter().find(|(pos, obj)| **obj == Obj::Robot).expect("Could not find robot").0;
     for mv in inputs {
-        let dir = mv.get_move();
-        if move_object(robot_pos, dir, &mut map) {
-            robot_pos = robot_pos + dir;
-        }
+        map.push(mv.get_move());
     }
-
-    map.iter()
-        .filter(|(_, obj)| **obj == Obj::Box)
-        .map(|(pos, _)| gps_coord(*pos))
-        .sum()
+    map
 }
 
-fn part2(map: &Map, inputs: &Inputs) -> i32 {
-    let mut map = widen_map(map);
-    let mut robot_pos = *map.iter().find(|(pos, obj)| **obj == Obj::Robot).expect("Could not find robot").0;
-    for mv in inputs {
-        let mut tmp_map = map.clone();
-        let dir = mv.get_move();
-        if move_object(robot_pos, dir, &mut tmp_map) {
-            robot_pos = robot_pos + dir;
-            map = tmp_map;
-        }
-    }
-
-    map.iter()
-        .filter(|(_, obj)| **obj == Obj::BoxLeft)
-        .map(|(pos, _)| gps_coord(*pos))
-        .sum()
+fn part1(map: &Warehouse, inputs: &Inputs) -> i32 {
+    run(map, inputs).gps_sum()
 }
 
+fn part2(map: &Warehouse, inputs: &Inputs) -> i32 {
+    run(&widen_map(map, 2), inputs).gps_sum()
+}
 
 pub fn day15() {
     let input = fs::read_to_string("inputs/day15.txt").expect("Could not read input");
@@ -190,6 +451,19 @@ pub fn day15() {
 
     println!("Part 1: {}", part1(&map, &inputs));
     println!("Part 2: {}", part2(&map, &inputs));
+
+    let mut sim = Simulator::new(widen_map(&map, 2));
+    match sim.run(&inputs) {
+        Ok(()) => {
+            let stuck = sim.log().iter().filter(|r| r.moved.is_none()).count();
+            println!(
+                "Checked {} moves, {} of them blocked",
+                sim.log().len(),
+                stuck
+            );
+        }
+        Err(e) => println!("Invariant broken: {e}"),
+    }
 }
 
 #[cfg(test)]
@@ -251,4 +525,181 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
 
         assert_eq!(9021, part2(&map, &inputs));
     }
-}
\ No newline at end of file
+
+    fn moves(input: &str) -> Inputs {
+        input.chars().filter_map(|c| c.try_into().ok()).collect()
+    }
+
+    #[test]
+    fn test_wide_boxes() {
+        let map = Warehouse::parse(
+            "#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######",
+        );
+        let map = run(&widen_map(&map, 2), &moves("<vv<<^^<<^^"));
+
+        assert_eq!(
+            "##############
+##...[].##..##
+##...@.[]...##
+##....[]....##
+##..........##
+##..........##
+##############
+",
+            map.to_string()
+        );
+        assert_eq!(105 + 207 + 306, map.gps_sum());
+    }
+
+    #[test]
+    fn test_scale() {
+        let map = Warehouse::parse(
+            "#####
+#.O@#
+#####",
+        );
+        let wide = widen_map(&map, 3);
+        assert_eq!(
+            vec![Vec2::new(6, 1), Vec2::new(7, 1), Vec2::new(8, 1)],
+            wide.blocks()[0].cells
+        );
+        assert_eq!(Vec2::new(9, 1), wide.robot());
+        assert_eq!(
+            "###############
+###...aaa@..###
+###############
+",
+            wide.to_string()
+        );
+
+        let mut wide = wide;
+        assert_eq!(Some(vec![0]), wide.push(Vec2::new(-1, 0)));
+        assert_eq!(Some(vec![0]), wide.push(Vec2::new(-1, 0)));
+        assert_eq!(Some(vec![0]), wide.push(Vec2::new(-1, 0)));
+        assert_eq!(None, wide.push(Vec2::new(-1, 0)));
+        assert_eq!(Vec2::new(6, 1), wide.robot());
+        assert_eq!(103, wide.gps_sum());
+    }
+
+    #[test]
+    fn test_move_log() {
+        let input = "#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^";
+        let (map, inputs) = parse_input(input);
+        let wide = widen_map(&map, 2);
+        let mut sim = Simulator::new(wide.clone());
+        sim.run(&inputs).unwrap();
+
+        assert_eq!(run(&wide, &inputs).to_string(), sim.map().to_string());
+        assert_eq!(11, sim.log().len());
+        assert_eq!(
+            MoveRecord {
+                input: Input::Left,
+                moved: Some(vec![1, 0])
+            },
+            sim.log()[0]
+        );
+        // The robot walks straight into a wall going up the second time
+        assert_eq!(None, sim.log()[6].moved);
+
+        let end = sim.map().to_string();
+        sim.seek(0);
+        assert_eq!(wide.to_string(), sim.map().to_string());
+        assert!(sim.check().is_ok());
+        assert_eq!(None, sim.undo());
+
+        sim.seek(11);
+        assert_eq!(end, sim.map().to_string());
+        assert_eq!(None, sim.redo());
+
+        // A new move after undoing throws away the moves that were undone
+        sim.undo();
+        sim.step(Input::Down).unwrap();
+        assert_eq!(None, sim.redo());
+        assert_eq!(Input::Down, sim.log()[10].input);
+    }
+
+    #[test]
+    fn test_invariants() {
+        let diagram = "#####
+#[].#
+#.@.#
+#####";
+        let mut sim = Simulator::new(Warehouse::parse(diagram));
+        assert!(sim.check().is_ok());
+
+        sim.map.blocks[0].cells[1] = Vec2::new(2, 2);
+        assert!(sim.check().unwrap_err().contains("under the robot"));
+
+        let mut sim = Simulator::new(Warehouse::parse(diagram));
+        sim.map.shift(&[0], Vec2::new(0, -1));
+        assert_eq!(
+            "Block 0 is inside a wall at (1, 0)",
+            sim.check().unwrap_err()
+        );
+
+        // Moving one half of a box on its own, as the old rollback bug could
+        let mut sim = Simulator::new(Warehouse::parse(diagram));
+        sim.map.occupied.remove(&Vec2::new(2, 1));
+        sim.map.occupied.insert(Vec2::new(3, 2), 0);
+        sim.map.blocks[0].cells[1] = Vec2::new(3, 2);
+        assert_eq!(
+            "Block 0 has been pulled out of shape",
+            sim.check().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_shapes() {
+        // A tall box b standing on an L-shaped box a, with the robot under the leg of the L
+        let mut map = Warehouse::parse(
+            "########
+#......#
+#.b....#
+#.b....#
+#aaa...#
+#a.....#
+#@.O...#
+########",
+        );
+        assert_eq!(3, map.blocks().len());
+        assert_eq!(2, map.blocks()[0].cells.len());
+        assert_eq!(4, map.blocks()[1].cells.len());
+
+        // Pushing up the L also lifts the tall box resting on it
+        assert_eq!(Some(vec![1, 0]), map.push(Vec2::new(0, -1)));
+        assert_eq!(Vec2::new(1, 5), map.robot());
+        // b is against the wall now, so neither can go up again
+        assert_eq!(None, map.push(Vec2::new(0, -1)));
+        assert_eq!(Vec2::new(1, 5), map.robot());
+        assert_eq!(Vec2::new(2, 1), map.blocks()[0].anchor());
+
+        // Pushing the top of a tall box sideways drags its lower half along too
+        let mut map = Warehouse::parse(
+            "#######
+#.....#
+#@ab..#
+#.a...#
+#######",
+        );
+        assert_eq!(Some(vec![0, 1]), map.push(Vec2::new(1, 0)));
+        assert_eq!(
+            vec![Vec2::new(3, 2), Vec2::new(3, 3)],
+            map.blocks()[0].cells
+        );
+        assert_eq!(vec![Vec2::new(4, 2)], map.blocks()[1].cells);
+    }
+}
@@ -3,15 +3,95 @@ use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs;
 
-type Path = HashSet<Vec2<i32>>;
+/// Facings in clockwise order, so turning is adding or subtracting one. The reindeer
+/// starts facing east.
+const DIRECTIONS: [Vec2<i32>; 4] = [
+    Vec2 { x: 1, y: 0 },
+    Vec2 { x: 0, y: 1 },
+    Vec2 { x: -1, y: 0 },
+    Vec2 { x: 0, y: -1 },
+];
+
+/// What each move of the reindeer costs. None of the costs may be negative.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostModel {
+    /// Stepping one tile forward
+    pub step: i32,
+    /// Turning 90 degrees on the spot
+    pub turn: i32,
+    /// Turning around on the spot
+    pub reverse: i32,
+    /// Extra cost of stepping onto a tile, by its character. Other tiles cost nothing extra
+    pub terrain: HashMap<char, i32>,
+    /// The way the reindeer has to face when it reaches the end, if it matters
+    pub end_facing: Option<Vec2<i32>>,
+}
+
+impl CostModel {
+    /// The costs from the puzzle: 1 per step, 1000 per quarter turn and any facing at the end.
+    pub fn puzzle() -> CostModel {
+        CostModel {
+            step: 1,
+            turn: 1000,
+            reverse: 2000,
+            terrain: HashMap::new(),
+            end_facing: None,
+        }
+    }
+
+    fn enter(&self, tile: char) -> i32 {
+        self.step + self.terrain.get(&tile).copied().unwrap_or(0)
+    }
+
+    fn end_dirs(&self) -> Vec<usize> {
+        match self.end_facing {
+            Some(facing) => vec![DIRECTIONS
+                .iter()
+                .position(|&d| d == facing)
+                .expect("End facing is not a direction")],
+            None => (0..4).collect(),
+        }
+    }
+}
+
+/// A maze as read from the map. `#` is a wall, `S` the start and `E` the end; every other
+/// character is open ground, possibly with a terrain cost.
+pub struct Maze {
+    start: Vec2<i32>,
+    end: Vec2<i32>,
+    width: i32,
+    height: i32,
+    tiles: Vec<char>,
+}
+
+impl Maze {
+    fn tile(&self, pos: Vec2<i32>) -> Option<char> {
+        if pos.in_bounds(Vec2::zero(), Vec2::new(self.width - 1, self.height - 1)) {
+            Some(self.tiles[(pos.y * self.width + pos.x) as usize])
+        } else {
+            None
+        }
+    }
+
+    fn is_open(&self, pos: Vec2<i32>) -> bool {
+        self.tile(pos).is_some_and(|c| c != '#')
+    }
+
+    /// Every `(pos, dir)` state has a slot in a flat array.
+    fn index(&self, pos: Vec2<i32>, dir: usize) -> usize {
+        (pos.y * self.width + pos.x) as usize * 4 + dir
+    }
+
+    fn states(&self) -> usize {
+        self.tiles.len() * 4
+    }
+}
 
 #[derive(PartialEq, Eq)]
 struct State {
     cost: i32,
-    dir: Vec2<i32>,
-    heuristic: i32,
     pos: Vec2<i32>,
-    path: Path,
+    dir: usize,
 }
 
 impl Ord for State {
@@ -26,115 +106,151 @@ impl PartialOrd for State {
     }
 }
 
-fn shortest_paths(start: Vec2<i32>, end: Vec2<i32>, obstacles: &HashSet<Vec2<i32>>) -> (Vec<Path>, i32) {
+/// Lowest cost from any of `sources` to every state, or, with `reverse`, from every state
+/// to any of `sources`. Unreachable states are left at `i32::MAX`.
+fn distances(
+    maze: &Maze,
+    costs: &CostModel,
+    sources: &[(Vec2<i32>, usize)],
+    reverse: bool,
+) -> Vec<i32> {
+    let mut dist = vec![i32::MAX; maze.states()];
     let mut heads = BinaryHeap::new();
-    heads.push(State {
-        cost: 0,
-        heuristic: (end - start).manhattan_distance(),
-        pos: start,
-        dir: Vec2::new(1, 0),
-        path: HashSet::from([start]),
-    });
-    let mut visited = HashMap::new();
-    let mut paths = Vec::new();
-
-    let all_dirs = Vec2::all_dirs();
-
-    let mut path_cost = i32::MAX;
 
-    loop {
-        let head = heads.pop().expect("No path");
-
-        visited.insert(head.pos, head.cost);
-
-        if head.cost > path_cost {
-            return (paths, path_cost);
+    for &(pos, dir) in sources {
+        if maze.is_open(pos) {
+            dist[maze.index(pos, dir)] = 0;
+            heads.push(State { cost: 0, pos, dir });
         }
+    }
 
-        if head.pos == end {
-            path_cost = head.cost;
-            paths.push(head.path);
+    while let Some(head) = heads.pop() {
+        if head.cost > dist[maze.index(head.pos, head.dir)] {
             continue;
         }
 
-        for &dir in &all_dirs {
-            let pos = head.pos + dir;
-            let cost = head.cost + if dir == head.dir { 1 } else { 1001 };
-
-            if obstacles.contains(&pos) {
+        // Stepping costs whatever the tile being entered costs, which going backwards
+        // is the tile the head is on
+        let (step, entered) = if reverse {
+            (head.pos - DIRECTIONS[head.dir], head.pos)
+        } else {
+            (
+                head.pos + DIRECTIONS[head.dir],
+                head.pos + DIRECTIONS[head.dir],
+            )
+        };
+        let next = [
+            (step, head.dir, maze.tile(entered).map(|c| costs.enter(c))),
+            (head.pos, (head.dir + 1) % 4, Some(costs.turn)),
+            (head.pos, (head.dir + 3) % 4, Some(costs.turn)),
+            (head.pos, (head.dir + 2) % 4, Some(costs.reverse)),
+        ];
+
+        for (pos, dir, cost) in next {
+            let Some(cost) = cost else { continue };
+            if !maze.is_open(pos) {
                 continue;
             }
+            assert!(cost >= 0, "Negative move cost {cost}");
 
-
-            if head.path.contains(&pos) {
-                continue;
+            let cost = head.cost + cost;
+            let i = maze.index(pos, dir);
+            if cost < dist[i] {
+                dist[i] = cost;
+                heads.push(State { cost, pos, dir });
             }
+        }
+    }
 
-            if let Some(&x) = visited.get(&pos) {
-                if cost > x + 1000 {
-                    continue;
-                }
-            }
-
-            let mut path = head.path.clone();
-            path.insert(pos);
-
+    dist
+}
 
-            heads.push(State {
-                cost,
-                heuristic: cost + (end - start).manhattan_distance(),
-                pos,
-                dir,
-                path,
+/// Cost of the cheapest route and every tile that lies on at least one cheapest route, or
+/// None if the end can't be reached. A state is on a cheapest route exactly when its
+/// distance from the start plus its distance to the end adds up to the best cost.
+pub fn shortest_paths(maze: &Maze, costs: &CostModel) -> Option<(HashSet<Vec2<i32>>, i32)> {
+    let ends: Vec<_> = costs
+        .end_dirs()
+        .into_iter()
+        .map(|dir| (maze.end, dir))
+        .collect();
+    let from_start = distances(maze, costs, &[(maze.start, 0)], false);
+    let to_end = distances(maze, costs, &ends, true);
+
+    let best = ends
+        .iter()
+        .map(|&(pos, dir)| from_start[maze.index(pos, dir)])
+        .min()
+        .filter(|&cost| cost < i32::MAX)?;
+
+    let mut tiles = HashSet::new();
+    for y in 0..maze.height {
+        for x in 0..maze.width {
+            let pos = Vec2::new(x, y);
+            let on_path = (0..4).any(|dir| {
+                let i = maze.index(pos, dir);
+                from_start[i] < i32::MAX
+                    && to_end[i] < i32::MAX
+                    && from_start[i] + to_end[i] == best
             });
+            if on_path {
+                tiles.insert(pos);
+            }
         }
     }
+
+    Some((tiles, best))
 }
 
-fn part1(start: Vec2<i32>, end: Vec2<i32>, obstacles: &HashSet<Vec2<i32>>) -> i32 {
-    let (_, len) = shortest_paths(start, end, &obstacles);
+fn part1(maze: &Maze) -> i32 {
+    let (_, len) = shortest_paths(maze, &CostModel::puzzle()).expect("No path");
     len
 }
 
-fn part2(start: Vec2<i32>, end: Vec2<i32>, obstacles: &HashSet<Vec2<i32>>) -> i32 {
-    let (paths, _) = shortest_paths(start, end, &obstacles);
-
-    println!("Num paths found: {}", paths.len());
-
-    HashSet::<Vec2<i32>>::from_iter(
-        paths
-            .iter()
-            .flat_map(|p|
-                p.iter().cloned()
-            ))
-        .len() as i32
+fn part2(maze: &Maze) -> i32 {
+    let (tiles, _) = shortest_paths(maze, &CostModel::puzzle()).expect("No path");
+    tiles.len() as i32
 }
 
-fn parse_input(input: &str) -> (Vec2<i32>, Vec2<i32>, HashSet<Vec2<i32>>) {
+fn parse_input(input: &str) -> Maze {
     let mut start = Vec2::zero();
     let mut end = Vec2::zero();
-    let mut obstacles = HashSet::new();
-
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
+    let lines: Vec<&str> = input.lines().collect();
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let mut tiles = Vec::with_capacity(width * lines.len());
+
+    for (y, line) in lines.iter().enumerate() {
+        for (x, c) in line
+            .chars()
+            .chain(std::iter::repeat('#'))
+            .take(width)
+            .enumerate()
+        {
             let pos = Vec2::new(x as i32, y as i32);
             match c {
-                '#' => { obstacles.insert(pos); }
                 'S' => start = pos,
                 'E' => end = pos,
-                _ => ()
+                _ => (),
             }
+            tiles.push(c);
         }
     }
 
-    (start, end, obstacles)
+    Maze {
+        start,
+        end,
+        width: width as i32,
+        height: lines.len() as i32,
+        tiles,
+    }
 }
+
 pub fn day16() {
     let input = fs::read_to_string("inputs/day16.txt").expect("Could not read input");
 
-    let (start, end, obstacles) = parse_input(&input);
-    println!("Part 1: {}", part1(start, end, &obstacles));
-    println!("Part 2: {}", part2(start, end, &obstacles));
+    let maze = parse_input(&input);
+    println!("Part 1: {}", part1(&maze));
+    println!("Part 2: {}", part2(&maze));
 }
 
 #[cfg(test)]
@@ -158,8 +274,8 @@ mod tests {
 #.###.#.#.#.#.#
 #S..#.....#...#
 ###############";
-        let (start, end, obstacles) = parse_input(&input);
-        assert_eq!(7036, part1(start, end, &obstacles));
+        let maze = parse_input(input);
+        assert_eq!(7036, part1(&maze));
     }
 
     #[test]
@@ -181,8 +297,8 @@ mod tests {
 #.#.#.#########.#
 #S#.............#
 #################";
-        let (start, end, obstacles) = parse_input(&input);
-        assert_eq!(11048, part1(start, end, &obstacles));
+        let maze = parse_input(input);
+        assert_eq!(11048, part1(&maze));
     }
 
     #[test]
@@ -202,8 +318,8 @@ mod tests {
 #.###.#.#.#.#.#
 #S..#.....#...#
 ###############";
-        let (start, end, obstacles) = parse_input(&input);
-        assert_eq!(45, part2(start, end, &obstacles));
+        let maze = parse_input(input);
+        assert_eq!(45, part2(&maze));
     }
 
     #[test]
@@ -225,7 +341,110 @@ mod tests {
 #.#.#.#########.#
 #S#.............#
 #################";
-        let (start, end, obstacles) = parse_input(&input);
-        assert_eq!(64, part2(start, end, &obstacles));
+        let maze = parse_input(input);
+        assert_eq!(64, part2(&maze));
+    }
+
+    #[test]
+    fn test_dead_end() {
+        // Facing a wall the reindeer has to turn twice to head back west
+        let maze = parse_input("#####\n#E.S#\n#####");
+        assert_eq!(2002, part1(&maze));
+        assert_eq!(3, part2(&maze));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_open_room() {
+        let size = 300;
+        let mut input = String::new();
+        for y in 0..size {
+            for x in 0..size {
+                let edge = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                input.push(match (x, y) {
+                    _ if edge => '#',
+                    (1, y) if y == size - 2 => 'S',
+                    (x, 1) if x == size - 2 => 'E',
+                    _ => '.',
+                });
+            }
+            input.push('\n');
+        }
+        let maze = parse_input(&input);
+
+        // Heading north first would take a second turn, so the only best route runs
+        // along the bottom wall and up the right one
+        let (tiles, best) = shortest_paths(&maze, &CostModel::puzzle()).unwrap();
+        assert_eq!(2 * (size - 3) + 1000, best);
+        assert_eq!(2 * (size - 3) + 1, tiles.len() as i32);
+        assert!(tiles.contains(&Vec2::new(size - 2, size - 2)));
+    }
+
+    #[test]
+    fn test_turn_costs() {
+        let maze = parse_input("#####\n#E.S#\n#####");
+        let mut costs = CostModel::puzzle();
+        costs.reverse = 500;
+        assert_eq!(
+            Some(502),
+            shortest_paths(&maze, &costs).map(|(_, best)| best)
+        );
+
+        // Turning around in one go is never worse than two quarter turns
+        costs.reverse = 5000;
+        assert_eq!(
+            Some(2002),
+            shortest_paths(&maze, &costs).map(|(_, best)| best)
+        );
+    }
+
+    #[test]
+    fn test_terrain() {
+        let maze = parse_input(
+            "#######
+#S.~.E#
+#.###.#
+#.....#
+#######",
+        );
+        let mut costs = CostModel::puzzle();
+        costs.step = 2;
+        assert_eq!(8, shortest_paths(&maze, &costs).unwrap().1);
+
+        costs.step = 1;
+        costs.terrain.insert('~', 3000);
+        let (tiles, best) = shortest_paths(&maze, &costs).unwrap();
+        assert_eq!((5, 3004), (tiles.len(), best));
+
+        // Three turns and a longer walk are cheaper than wading through the swamp
+        costs.terrain.insert('~', 5000);
+        let (tiles, best) = shortest_paths(&maze, &costs).unwrap();
+        assert_eq!((9, 3008), (tiles.len(), best));
+        assert!(!tiles.contains(&Vec2::new(3, 1)));
+    }
+
+    #[test]
+    fn test_end_facing() {
+        let maze = parse_input(
+            "#######
+#S...E#
+#.###.#
+#.....#
+#######",
+        );
+        let mut costs = CostModel::puzzle();
+        costs.end_facing = Some(Vec2::new(0, 1));
+        assert_eq!(
+            Some((5, 1004)),
+            shortest_paths(&maze, &costs).map(|(t, best)| (t.len(), best))
+        );
+
+        costs.end_facing = Some(Vec2::new(-1, 0));
+        assert_eq!(
+            Some(2004),
+            shortest_paths(&maze, &costs).map(|(_, best)| best)
+        );
+
+        let walled = parse_input("#####\n#S#E#\n#####");
+        assert_eq!(None, shortest_paths(&walled, &CostModel::puzzle()));
+    }
+}
@@ -1,6 +1,7 @@
-﻿use crate::util::vec2::Vec2;
+﻿use crate::util::union_find::UnionFind;
+use crate::util::vec2::Vec2;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fs;
 
 #[derive(PartialEq, Eq)]
@@ -22,7 +23,13 @@ impl PartialOrd for State {
     }
 }
 
-fn shortest_path(start: Vec2<i32>, end: Vec2<i32>, obstacles: &HashSet<Vec2<i32>>, min: Vec2<i32>, max: Vec2<i32>) -> Option<i32> {
+fn shortest_path(
+    start: Vec2<i32>,
+    end: Vec2<i32>,
+    obstacles: &HashSet<Vec2<i32>>,
+    min: Vec2<i32>,
+    max: Vec2<i32>,
+) -> Option<i32> {
     let mut heads = BinaryHeap::new();
     heads.push(State {
         cost: 0,
@@ -57,7 +64,6 @@ fn shortest_path(start: Vec2<i32>, end: Vec2<i32>, obstacles: &HashSet<Vec2<i32>
 
             let cost = head.cost + 1;
 
-
             heads.push(State {
                 cost,
                 heuristic: cost + (end - start).manhattan_distance(),
@@ -69,31 +75,222 @@ fn shortest_path(start: Vec2<i32>, end: Vec2<i32>, obstacles: &HashSet<Vec2<i32>
 
 fn part1(falling: &Vec<Vec2<i32>>, num_steps: usize, size: Vec2<i32>) -> i32 {
     let end = size;
-    shortest_path(Vec2::zero(), end, &HashSet::from_iter(falling.iter().take(num_steps).cloned()), Vec2::zero(), end).unwrap()
+    shortest_path(
+        Vec2::zero(),
+        end,
+        &HashSet::from_iter(falling.iter().take(num_steps).cloned()),
+        Vec2::zero(),
+        end,
+    )
+    .unwrap()
 }
 
 fn parse_input(input: &str) -> Vec<Vec2<i32>> {
-    input.lines()
+    input
+        .lines()
         .filter_map(|l| l.split_once(","))
         .map(|(x, y)| Vec2::new(x.parse().unwrap(), y.parse().unwrap()))
         .collect()
 }
 
-fn part2(falling: &Vec<Vec2<i32>>, size: Vec2<i32>) -> Vec2<i32> {
-    let mut min = 0;
-    let mut max = falling.len() - 1;
-    loop {
-        let i = (max + min) / 2;
-        let obstacles = &HashSet::from_iter(falling.iter().take(i).cloned());
-        if shortest_path(Vec2::zero(), size, &obstacles, Vec2::zero(), size).is_some() {
-            min = max.min(i + 1);
-        } else {
-            max = min.max(max - 1);
+/// Cells of the `size` grid, numbered row by row, or None for cells outside it.
+fn cell_index(pos: Vec2<i32>, size: Vec2<i32>) -> Option<usize> {
+    if pos.in_bounds(Vec2::zero(), size) {
+        Some((pos.y * (size.x + 1) + pos.x) as usize)
+    } else {
+        None
+    }
+}
+
+/// Index of the first byte to land on each cell, which stays blocked from then on.
+fn landing_times(falling: &[Vec2<i32>], size: Vec2<i32>) -> Vec<Option<usize>> {
+    let mut landed = vec![None; cell_index(size, size).unwrap() + 1];
+    for (i, &byte) in falling.iter().enumerate() {
+        if let Some(cell) = cell_index(byte, size) {
+            landed[cell].get_or_insert(i);
+        }
+    }
+    landed
+}
+
+/// Index of the first byte after which there is no way from the top left to the bottom
+/// right corner, or None if the bytes never cut it off. Runs the fall backwards, lifting
+/// the bytes one by one and joining each freed cell to its open neighbours, so the answer
+/// is the byte that first reconnects the corners.
+pub fn first_blocking(falling: &[Vec2<i32>], size: Vec2<i32>) -> Option<usize> {
+    let landed = landing_times(falling, size);
+    let cells = landed.len();
+
+    let mut sets = UnionFind::new(cells);
+    let mut open: Vec<bool> = landed.iter().map(Option::is_none).collect();
+    let (start, end) = (0, cells - 1);
+
+    let free = |pos: Vec2<i32>, open: &mut Vec<bool>, sets: &mut UnionFind| {
+        let cell = cell_index(pos, size).unwrap();
+        open[cell] = true;
+        for dir in Vec2::all_dirs() {
+            if let Some(next) = cell_index(pos + dir, size) {
+                if open[next] {
+                    sets.union(cell, next);
+                }
+            }
+        }
+    };
+
+    for y in 0..=size.y {
+        for x in 0..=size.x {
+            let pos = Vec2::new(x, y);
+            if open[cell_index(pos, size).unwrap()] {
+                free(pos, &mut open, &mut sets);
+            }
+        }
+    }
+    if open[start] && open[end] && sets.same(start, end) {
+        return None;
+    }
+
+    for (i, &byte) in falling.iter().enumerate().rev() {
+        let Some(cell) = cell_index(byte, size) else {
+            continue;
+        };
+        if landed[cell] != Some(i) {
+            continue;
+        }
+
+        free(byte, &mut open, &mut sets);
+        if open[start] && open[end] && sets.same(start, end) {
+            return Some(i);
+        }
+    }
+
+    unreachable!("The corners are connected once every byte is lifted")
+}
+
+/// A shortest route from `start` to `end`, both included, found by breadth first search.
+fn route(
+    start: Vec2<i32>,
+    end: Vec2<i32>,
+    obstacles: &HashSet<Vec2<i32>>,
+    min: Vec2<i32>,
+    max: Vec2<i32>,
+) -> Option<Vec<Vec2<i32>>> {
+    let mut came_from = HashMap::from([(start, start)]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(pos) = queue.pop_front() {
+        if pos == end {
+            let mut path = vec![end];
+            while *path.last().unwrap() != start {
+                path.push(came_from[path.last().unwrap()]);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for dir in Vec2::all_dirs() {
+            let next = pos + dir;
+            if next.in_bounds(min, max)
+                && !obstacles.contains(&next)
+                && !came_from.contains_key(&next)
+            {
+                came_from.insert(next, pos);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Length of the shortest path after each number of fallen bytes, from none up to all of
+/// them, with None once the exit is cut off. A new search only runs when a byte lands on
+/// the current route.
+pub fn path_lengths(falling: &[Vec2<i32>], size: Vec2<i32>) -> Vec<Option<i32>> {
+    let blocked_from = first_blocking(falling, size).unwrap_or(falling.len());
+    let mut obstacles = HashSet::new();
+    let mut path: HashSet<Vec2<i32>> = HashSet::new();
+    let mut lengths = Vec::with_capacity(falling.len() + 1);
+
+    for fallen in 0..=falling.len() {
+        if fallen > 0 {
+            obstacles.insert(falling[fallen - 1]);
+        }
+        if fallen > blocked_from {
+            lengths.push(None);
+            continue;
+        }
+
+        if fallen == 0 || path.contains(&falling[fallen - 1]) {
+            let steps = route(Vec2::zero(), size, &obstacles, Vec2::zero(), size)
+                .expect("Path blocked early");
+            path = steps.into_iter().collect();
+        }
+        lengths.push(Some(path.len() as i32 - 1));
+    }
+
+    lengths
+}
+
+/// Where the walker is at every time step of a walk, from the start at time 0 to the exit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Walk {
+    pub arrival: usize,
+    pub route: Vec<Vec2<i32>>,
+}
+
+/// Earliest way from the top left to the bottom right corner when `fallen` bytes are down
+/// at the start and another one lands with every step, so the first `fallen + t` bytes
+/// are down at time `t`. The walker may step off a cell as a byte lands on it but never
+/// onto a fallen byte.
+///
+/// Breadth first search over `(pos, t)`, one layer per time step. Bytes never lift, so
+/// anything the walker can do after reaching a cell late it can also do after reaching it
+/// first, and only the first arrival at each cell is kept.
+pub fn walk_while_falling(falling: &[Vec2<i32>], fallen: usize, size: Vec2<i32>) -> Option<Walk> {
+    let landed = landing_times(falling, size);
+    let is_open = |pos: Vec2<i32>, t: usize| {
+        pos.in_bounds(Vec2::zero(), size)
+            && landed[cell_index(pos, size).unwrap()].is_none_or(|i| i >= fallen + t)
+    };
+    let (start, end) = (Vec2::zero(), size);
+    if !is_open(start, 0) {
+        return None;
+    }
+
+    // Where the walker first got to each cell from. The frontier holds the cells first
+    // reached at time `t`, so no cell is expanded twice
+    let mut came_from = HashMap::from([(start, start)]);
+    let mut frontier = vec![start];
+    for t in 0.. {
+        if frontier.is_empty() {
+            return None;
         }
-        if max == min {
-            return falling[max - 1];
+        if frontier.contains(&end) {
+            let mut route = vec![end];
+            while *route.last().unwrap() != start {
+                route.push(came_from[route.last().unwrap()]);
+            }
+            route.reverse();
+            return Some(Walk { arrival: t, route });
+        }
+
+        let mut next = Vec::new();
+        for pos in frontier {
+            for dir in Vec2::all_dirs() {
+                if is_open(pos + dir, t + 1) && !came_from.contains_key(&(pos + dir)) {
+                    came_from.insert(pos + dir, pos);
+                    next.push(pos + dir);
+                }
+            }
         }
+        frontier = next;
     }
+
+    unreachable!()
+}
+
+fn part2(falling: &[Vec2<i32>], size: Vec2<i32>) -> Vec2<i32> {
+    falling[first_blocking(falling, size).expect("The path is never blocked")]
 }
 
 pub fn day18() {
@@ -103,12 +300,55 @@ pub fn day18() {
 
     println!("Part 1: {}", part1(&falling, 1024, Vec2::new(70, 70)));
     println!("Part 2: {}", part2(&falling, Vec2::new(70, 70)));
+
+    let lengths = path_lengths(&falling, Vec2::new(70, 70));
+    let open: Vec<i32> = lengths.iter().flatten().copied().collect();
+    println!(
+        "Shortest path grows from {} to {} steps over {} bytes",
+        open[0],
+        open[open.len() - 1],
+        open.len() - 1
+    );
+
+    match walk_while_falling(&falling, 1024, Vec2::new(70, 70)) {
+        Some(walk) => println!(
+            "Out after {} steps with a byte landing every step",
+            walk.arrival
+        ),
+        None => println!("No way out with a byte landing every step"),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: &str = "5,4
+4,2
+4,5
+3,0
+2,1
+6,3
+2,4
+1,5
+0,6
+3,3
+2,6
+5,1
+1,2
+5,5
+2,5
+6,5
+1,4
+0,4
+6,4
+1,1
+6,1
+1,0
+0,5
+1,6
+2,0";
+
     #[test]
     fn test_p1() {
         let input = "5,4
@@ -170,4 +410,101 @@ mod tests {
         let falling = parse_input(&input);
         assert_eq!(Vec2::new(6, 1), part2(&falling, Vec2::new(6, 6)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_path_lengths() {
+        let falling = parse_input(SAMPLE);
+        let size = Vec2::new(6, 6);
+        let lengths = path_lengths(&falling, size);
+
+        assert_eq!(falling.len() + 1, lengths.len());
+        assert_eq!(Some(12), lengths[0]);
+        assert_eq!(Some(22), lengths[12]);
+        for (fallen, &length) in lengths.iter().enumerate() {
+            let obstacles = HashSet::from_iter(falling.iter().take(fallen).cloned());
+            assert_eq!(
+                shortest_path(Vec2::zero(), size, &obstacles, Vec2::zero(), size),
+                length
+            );
+        }
+        assert_eq!(Some(20), first_blocking(&falling, size));
+        assert_eq!(None, lengths[21]);
+    }
+
+    #[test]
+    fn test_first_blocking() {
+        let size = Vec2::new(2, 2);
+        // Bytes outside the grid and repeats change nothing
+        let falling = [
+            Vec2::new(1, 0),
+            Vec2::new(9, 9),
+            Vec2::new(1, 0),
+            Vec2::new(1, 1),
+            Vec2::new(1, 2),
+        ];
+        assert_eq!(Some(4), first_blocking(&falling, size));
+        assert_eq!(None, first_blocking(&falling[..4], size));
+
+        // A byte on a corner blocks the path by itself
+        assert_eq!(
+            Some(1),
+            first_blocking(&[Vec2::new(1, 1), Vec2::new(2, 2)], size)
+        );
+    }
+
+    fn assert_valid(walk: &Walk, falling: &[Vec2<i32>], fallen: usize, size: Vec2<i32>) {
+        assert_eq!(walk.arrival + 1, walk.route.len());
+        assert_eq!(
+            (Vec2::zero(), size),
+            (walk.route[0], walk.route[walk.arrival])
+        );
+        for (t, &pos) in walk.route.iter().enumerate() {
+            assert!(pos.in_bounds(Vec2::zero(), size));
+            assert!(!falling[..falling.len().min(fallen + t)].contains(&pos));
+            if t > 0 {
+                assert_eq!(1, (pos - walk.route[t - 1]).manhattan_distance());
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_while_falling() {
+        let falling = parse_input(SAMPLE);
+        let size = Vec2::new(6, 6);
+
+        // Bytes that land behind the walker don't slow it down
+        let walk = walk_while_falling(&falling, 0, size).unwrap();
+        assert_eq!(12, walk.arrival);
+        assert_valid(&walk, &falling, 0, size);
+
+        for fallen in [3, 4, 9] {
+            let walk = walk_while_falling(&falling, fallen, size).unwrap();
+            let obstacles = HashSet::from_iter(falling.iter().take(fallen).cloned());
+            let before = shortest_path(Vec2::zero(), size, &obstacles, Vec2::zero(), size).unwrap();
+            assert!(walk.arrival as i32 >= before);
+            assert_valid(&walk, &falling, fallen, size);
+        }
+        // The bytes still to come cut off the exit long before the 21st does on its own
+        assert_eq!(22, walk_while_falling(&falling, 9, size).unwrap().arrival);
+        assert_eq!(None, walk_while_falling(&falling, 10, size));
+    }
+
+    #[test]
+    fn test_outrun_bytes() {
+        let size = Vec2::new(2, 2);
+        let falling = [Vec2::new(2, 0), Vec2::new(0, 1), Vec2::new(1, 0)];
+
+        // With every byte down the start is walled in, but the walker gets out before that
+        let obstacles = HashSet::from_iter(falling.iter().cloned());
+        assert_eq!(
+            None,
+            shortest_path(Vec2::zero(), size, &obstacles, Vec2::zero(), size)
+        );
+        let walk = walk_while_falling(&falling, 0, size).unwrap();
+        assert_eq!(4, walk.arrival);
+        assert_valid(&walk, &falling, 0, size);
+
+        assert_eq!(None, walk_while_falling(&falling, 2, size));
+        assert_eq!(None, walk_while_falling(&[Vec2::zero()], 1, size));
+    }
+}
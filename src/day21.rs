@@ -1,126 +1,460 @@
-﻿use crate::util::vec2::Vec2;
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
-use std::{fs, iter};
-
-#[derive(Clone, PartialEq, Eq)]
-enum Input {
-    Left,
-    Right,
-    Up,
-    Down,
-    A,
+use crate::util::matrix::Matrix;
+use crate::util::vec2::Vec2;
+use num_bigint::BigUint;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::ops::{Add, Mul};
+use std::str::FromStr;
+
+/// Why a keypad layout can't be used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeypadError {
+    DuplicateButton(char),
+    /// A button the door or a remote needs that its layout lacks
+    MissingButton(char),
+    /// Some button can't be reached from the others without passing over a gap
+    Disconnected(char),
 }
 
-impl Display for Input {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl Display for KeypadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Input::Left => write!(f, "<"),
-            Input::Right => write!(f, ">"),
-            Input::Up => write!(f, "^"),
-            Input::Down => write!(f, "v"),
-            Input::A => write!(f, "A"),
+            KeypadError::DuplicateButton(c) => write!(f, "button `{c}` appears more than once"),
+            KeypadError::MissingButton(c) => write!(f, "keypad is missing `{c}`"),
+            KeypadError::Disconnected(c) => write!(f, "button `{c}` can't be reached"),
+        }
+    }
+}
+
+/// Button positions of a keypad, with `(0, 0)` in the top left corner. Robot arms must
+/// never pass over a gap.
+#[derive(Clone)]
+struct Keypad {
+    buttons: HashMap<char, Vec2<i32>>,
+    gaps: HashSet<Vec2<i32>>,
+}
+
+impl Keypad {
+    /// Parses a diagram with one character per cell, where `#` and spaces mark cells the
+    /// arm must not pass over.
+    fn parse(diagram: &str) -> Result<Keypad, KeypadError> {
+        let mut buttons = HashMap::new();
+        let mut gaps = HashSet::new();
+
+        for (y, line) in diagram.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let pos = Vec2::new(x as i32, y as i32);
+                if c == '#' || c == ' ' {
+                    gaps.insert(pos);
+                } else if buttons.insert(c, pos).is_some() {
+                    return Err(KeypadError::DuplicateButton(c));
+                }
+            }
+        }
+
+        Ok(Keypad { buttons, gaps })
+    }
+
+    fn numeric() -> Keypad {
+        Keypad::parse(
+            "789
+456
+123
+#0A",
+        )
+        .expect("Numeric keypad is valid")
+    }
+
+    fn directional() -> Keypad {
+        Keypad::parse(
+            "#^A
+<v>",
+        )
+        .expect("Directional keypad is valid")
+    }
+
+    fn position(&self, button: char) -> Vec2<i32> {
+        *self
+            .buttons
+            .get(&button)
+            .unwrap_or_else(|| panic!("Invalid button `{button}`"))
+    }
+
+    fn button_at(&self, pos: Vec2<i32>) -> char {
+        self.buttons
+            .iter()
+            .find(|(_, &p)| p == pos)
+            .map(|(&c, _)| c)
+            .unwrap_or_else(|| panic!("No button at {pos}"))
+    }
+
+    fn is_bad(&self, pos: Vec2<i32>) -> bool {
+        self.gaps.contains(&pos) || !self.buttons.values().any(|&p| p == pos)
+    }
+
+    /// Checks that every button can be reached from every other one.
+    fn check_connected(&self) -> Result<(), KeypadError> {
+        let Some(&start) = self.buttons.values().next() else {
+            return Ok(());
+        };
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(pos) = stack.pop() {
+            for step in Vec2::all_dirs() {
+                let next = pos + step;
+                if !self.is_bad(next) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        match self.buttons.iter().find(|(_, pos)| !seen.contains(pos)) {
+            Some((&c, _)) => Err(KeypadError::Disconnected(c)),
+            None => Ok(()),
         }
     }
 }
 
-fn repeat<T: Clone>(num_times: i32, when_positive: T, when_negative: T) -> Vec<T> {
-    if num_times > 0 {
-        iter::repeat(when_positive)
-    } else {
-        iter::repeat(when_negative)
+fn direction(delta: Vec2<i32>) -> char {
+    match delta {
+        Vec2 { x: -1, y: 0 } => '<',
+        Vec2 { x: 1, y: 0 } => '>',
+        Vec2 { x: 0, y: -1 } => '^',
+        Vec2 { x: 0, y: 1 } => 'v',
+        _ => panic!("Invalid delta"),
     }
-    .take(num_times.abs() as usize)
-    .collect()
 }
 
-fn input_sequences(targets: &Vec<Vec2<i32>>, pos: Vec2<i32>, bad: Vec2<i32>) -> Vec<Input> {
-    let mut current = pos;
+/// Anything press counts can be added up in
+trait Presses: Clone + Ord + Add<Output = Self> + From<u8> {}
+
+impl<T: Clone + Ord + Add<Output = T> + From<u8>> Presses for T {}
 
-    let mut inputs: Vec<Input> = Vec::new();
+/// A code is typed on `pads[0]` by a robot, every other pad steers the robot at the pad
+/// before it, and the last pad is pressed by hand.
+/// Press counts grow exponentially with the number of robots, so they can be counted in
+/// anything from `i64` to `BigUint`.
+struct KeypadChain<T = i64> {
+    pads: Vec<Keypad>,
+    /// Cost and route of each move, see `cost`
+    memo: HashMap<(usize, char, char), (T, String)>,
+}
 
-    for &target in targets {
-        let delta = target - current;
+/// The buttons every remote needs, and the only ones its arm moves between
+const REMOTE_BUTTONS: [char; 5] = ['<', '>', '^', 'v', 'A'];
 
-        let x_moves = repeat(delta.x, Input::Right, Input::Left);
-        let y_moves = repeat(delta.y, Input::Down, Input::Up);
+/// Arm position and the button last pressed on the controlling pad.
+type ArmState = (i32, i32, char);
 
-        if (current.y == bad.y && target.x == bad.x) {
-            inputs.extend(y_moves.iter().cloned());
-            inputs.extend(x_moves.iter().cloned());
-        } else {
-            inputs.extend(x_moves.iter().cloned());
-            inputs.extend(y_moves.iter().cloned());
+impl<T: Presses> KeypadChain<T> {
+    /// `robots` is the number of robots between the door robot and the human, each
+    /// standing at a copy of `remote`. Fails unless the door has an A button, the remote
+    /// has every direction and A, and neither has buttons cut off by gaps.
+    fn new(door: Keypad, remote: Keypad, robots: usize) -> Result<KeypadChain<T>, KeypadError> {
+        if !door.buttons.contains_key(&'A') {
+            return Err(KeypadError::MissingButton('A'));
+        }
+        if let Some(&button) = REMOTE_BUTTONS
+            .iter()
+            .find(|b| !remote.buttons.contains_key(b))
+        {
+            return Err(KeypadError::MissingButton(button));
+        }
+        door.check_connected()?;
+        remote.check_connected()?;
+
+        let mut pads = vec![door];
+        pads.extend(std::iter::repeat_n(remote, robots + 1));
+
+        let mut chain = KeypadChain {
+            pads,
+            memo: HashMap::new(),
         };
+        // Costs of the remotes from the human end first, so that deep chains don't
+        // recurse through every layer at once
+        for layer in (1..chain.pads.len() - 1).rev() {
+            for from in REMOTE_BUTTONS {
+                for to in REMOTE_BUTTONS {
+                    chain.cost(layer, from, to);
+                }
+            }
+        }
+        Ok(chain)
+    }
+
+    /// Number of presses by hand needed to type `code`, with every arm starting at A.
+    fn presses(&mut self, code: &str) -> T {
+        let mut prev = 'A';
+        let mut res = T::from(0);
+
+        for c in code.chars() {
+            res = res + self.cost(0, prev, c);
+            prev = c;
+        }
+
+        res
+    }
 
-        inputs.push(Input::A);
-        current = target;
+    /// One optimal way to type `code`. The first string is the code itself, each following
+    /// string is typed on the next pad and the last one is pressed by hand. The strings
+    /// grow exponentially with the number of robots.
+    fn sequences(&mut self, code: &str) -> Vec<String> {
+        let mut res = vec![code.to_string()];
+
+        for layer in 0..self.pads.len() - 1 {
+            let mut prev = 'A';
+            let mut next = String::new();
+
+            for c in res[layer].clone().chars() {
+                next += &self.route(layer, prev, c);
+                prev = c;
+            }
+
+            res.push(next);
+        }
+
+        res
+    }
+
+    /// Number of presses by hand needed to move the arm over `pads[layer]` from `from` to
+    /// `to` and press it. All arms of the later pads start and end at A.
+    fn cost(&mut self, layer: usize, from: char, to: char) -> T {
+        if layer == self.pads.len() - 1 {
+            return T::from(1);
+        }
+
+        if let Some((cached, _)) = self.memo.get(&(layer, from, to)) {
+            return cached.clone();
+        }
+
+        let from_pos = self.pads[layer].position(from);
+        let to_pos = self.pads[layer].position(to);
+        let (res, route) = self
+            .walk(layer, from_pos, to_pos)
+            .expect("Keypads are connected, see `new`");
+
+        self.memo.insert((layer, from, to), (res.clone(), route));
+        res
+    }
+
+    /// The buttons pressed on `pads[layer + 1]` by the cheapest move found by `cost`.
+    fn route(&mut self, layer: usize, from: char, to: char) -> String {
+        self.cost(layer, from, to);
+        self.memo[&(layer, from, to)].1.clone()
+    }
+
+    /// Cheapest way to steer the arm over `pads[layer]` from `from` to `to` without
+    /// passing over a gap, along with the buttons pressed on the controlling pad.
+    fn walk(&mut self, layer: usize, from: Vec2<i32>, to: Vec2<i32>) -> Option<(T, String)> {
+        let start: ArmState = (from.x, from.y, 'A');
+        // The second field marks whether the button at `to` has been pressed
+        let mut heads = BinaryHeap::from([Reverse((T::from(0), false, start, start))]);
+        let mut parents: HashMap<ArmState, ArmState> = HashMap::new();
+
+        while let Some(Reverse((cost, pressed, state, parent))) = heads.pop() {
+            if pressed {
+                let mut route = vec!['A'];
+                let mut state = parent;
+                while state != start {
+                    route.push(state.2);
+                    state = parents[&state];
+                }
+                return Some((cost, route.iter().rev().collect()));
+            }
+
+            if parents.contains_key(&state) {
+                continue;
+            }
+            parents.insert(state, parent);
+
+            let (x, y, prev) = state;
+            let pos = Vec2::new(x, y);
+
+            if pos == to {
+                let press = self.cost(layer + 1, prev, 'A');
+                heads.push(Reverse((cost.clone() + press, true, state, state)));
+            }
+
+            for step in Vec2::all_dirs() {
+                let next = pos + step;
+                if self.pads[layer].is_bad(next) {
+                    continue;
+                }
+
+                let dir = direction(step);
+                let move_cost = self.cost(layer + 1, prev, dir);
+                heads.push(Reverse((
+                    cost.clone() + move_cost,
+                    false,
+                    (next.x, next.y, dir),
+                    state,
+                )));
+            }
+        }
+
+        None
     }
 
-    inputs
+    /// Presses `presses` by hand and returns what gets typed on the first pad, or an error
+    /// if an arm is steered over a gap.
+    fn replay(&self, presses: &str) -> Result<String, String> {
+        let mut arms: Vec<_> = self.pads.iter().map(|p| p.position('A')).collect();
+        let mut typed = String::new();
+
+        for (i, press) in presses.chars().enumerate() {
+            let mut button = press;
+
+            for layer in (0..self.pads.len() - 1).rev() {
+                if button != 'A' {
+                    let step = match button {
+                        '<' => Vec2::new(-1, 0),
+                        '>' => Vec2::new(1, 0),
+                        '^' => Vec2::new(0, -1),
+                        'v' => Vec2::new(0, 1),
+                        _ => return Err(format!("Press {i}: `{button}` is not a direction")),
+                    };
+                    arms[layer] = arms[layer] + step;
+                    if self.pads[layer].is_bad(arms[layer]) {
+                        return Err(format!(
+                            "Press {i}: arm {layer} moved over a gap at {}",
+                            arms[layer]
+                        ));
+                    }
+                    break;
+                }
+
+                button = self.pads[layer].button_at(arms[layer]);
+                if layer == 0 {
+                    typed.push(button);
+                }
+            }
+        }
+
+        Ok(typed)
+    }
 }
 
-fn keypad_sequences(code: &str) -> Vec<Input> {
-    let targets = code
-        .chars()
-        .map(|c| match c {
-            '7' => Vec2::new(0, 0),
-            '8' => Vec2::new(1, 0),
-            '9' => Vec2::new(2, 0),
-            '4' => Vec2::new(0, 1),
-            '5' => Vec2::new(1, 1),
-            '6' => Vec2::new(2, 1),
-            '1' => Vec2::new(0, 2),
-            '2' => Vec2::new(1, 2),
-            '3' => Vec2::new(2, 2),
-            '0' => Vec2::new(1, 3),
-            'A' => Vec2::new(2, 3),
-            _ => panic!("Invalid input `{c}`"),
-        })
-        .collect();
+fn complexity<T: Presses + Mul<Output = T> + FromStr>(code: &str, presses: T) -> T {
+    let num_code: T = code[..code.len() - 1]
+        .parse()
+        .unwrap_or_else(|_| panic!("Could not parse numeric part of {code}"));
+    presses * num_code
+}
+
+fn enter_code<T: Presses + Mul<Output = T> + FromStr>(codes: &Vec<&str>, robots: usize) -> T {
+    let mut chain: KeypadChain<T> =
+        KeypadChain::new(Keypad::numeric(), Keypad::directional(), robots)
+            .expect("Puzzle keypads are valid");
 
-    input_sequences(&targets, Vec2::new(2, 3), Vec2::new(0, 3))
+    codes.iter().fold(T::from(0), |total, code| {
+        let presses = chain.presses(code);
+        total + complexity(code, presses)
+    })
 }
 
-fn dir_pad_sequences(code: &Vec<Input>) -> Vec<Input> {
-    let targets = code
+/// Number of robots after which the cheapest route of every move is expected to stop
+/// changing as more robots are added.
+const STABLE_DEPTH: usize = 16;
+
+/// Presses needed to type `code` through `robots` robots, for chains too deep to expand
+/// layer by layer. Once the routes have settled, every remote reuses the same routes, so
+/// the cost of a move is a fixed combination of the move costs one layer closer to the
+/// human and the deep part of the chain becomes a power of that transition matrix.
+/// Falls back to walking every layer when the routes at `STABLE_DEPTH` still change with
+/// one more robot. Routes that survive that one extra robot are assumed to stay fixed for
+/// good, which is a heuristic rather than a proof; `test_deep_chains` holds it to the
+/// exact count of every layer for several codes at depths up to 1000.
+fn deep_presses(
+    door: &Keypad,
+    remote: &Keypad,
+    robots: usize,
+    code: &str,
+) -> Result<BigUint, KeypadError> {
+    let chain =
+        |door: &Keypad, robots| KeypadChain::<BigUint>::new(door.clone(), remote.clone(), robots);
+    let exact = || Ok(chain(door, robots)?.presses(code));
+    if robots <= STABLE_DEPTH {
+        return exact();
+    }
+
+    let moves: Vec<_> = REMOTE_BUTTONS
         .iter()
-        .map(|c| match c {
-            Input::Up => Vec2::new(1, 0),
-            Input::A => Vec2::new(2, 0),
-            Input::Left => Vec2::new(0, 1),
-            Input::Down => Vec2::new(1, 1),
-            Input::Right => Vec2::new(2, 1),
-        })
+        .flat_map(|&a| REMOTE_BUTTONS.iter().map(move |&b| (a, b)))
         .collect();
 
-    input_sequences(&targets, Vec2::new(2, 0), Vec2::new(0, 0))
-}
+    let count_moves = |presses: &str, counts: &mut Vec<BigUint>| {
+        let mut prev = 'A';
+        for c in presses.chars() {
+            let i = moves.iter().position(|&m| m == (prev, c)).unwrap();
+            counts[i] += 1u8;
+            prev = c;
+        }
+    };
 
-fn part1(codes: &Vec<&str>) -> i64 {
-    let mut total_complexity = 0;
-    for &code in codes {
-        let ks = keypad_sequences(code);
-        let dpr1s: Vec<_> = dir_pad_sequences(&ks);
-        let dpms: Vec<_> = dir_pad_sequences(&dpr1s);
-
-        println!(
-            "{code}: {}",
-            dpms.iter()
-                .map(|c| c.to_string())
-                .collect::<Vec<_>>()
-                .join("")
-        );
+    let mut stable = chain(remote, STABLE_DEPTH)?;
+    let mut transitions = Matrix::zero(moves.len());
+    for (i, &(from, to)) in moves.iter().enumerate() {
+        let route = stable.route(0, from, to);
+        if route != stable.route(1, from, to) {
+            return exact();
+        }
 
-        let num_code: i64 = code[..code.len() - 1]
-            .parse()
-            .expect("Could not parse numeric part of code");
-        let complexity = dpms.len() as i64 * num_code;
-        total_complexity += complexity;
+        let mut counts = vec![BigUint::default(); moves.len()];
+        count_moves(&route, &mut counts);
+        for (j, count) in counts.into_iter().enumerate() {
+            transitions.set(i, j, count);
+        }
     }
-    // Too high: 157596
 
-    total_complexity
+    // Exact move costs on the remote STABLE_DEPTH layers away from the human
+    let mut near = chain(remote, STABLE_DEPTH - 1)?;
+    let near_costs: Vec<_> = moves
+        .iter()
+        .map(|&(from, to)| near.cost(0, from, to))
+        .collect();
+
+    // Move costs on the first remote, right behind the door
+    let costs = transitions
+        .pow((robots - STABLE_DEPTH) as u64)
+        .mul_vec(&near_costs);
+
+    let mut door_chain = chain(door, STABLE_DEPTH)?;
+    let mut deeper_door_chain = chain(door, STABLE_DEPTH + 1)?;
+    let mut first_remote = vec![BigUint::default(); moves.len()];
+    let mut prev = 'A';
+    for c in code.chars() {
+        let route = door_chain.route(0, prev, c);
+        if route != deeper_door_chain.route(0, prev, c) {
+            return exact();
+        }
+        count_moves(&route, &mut first_remote);
+        prev = c;
+    }
+
+    Ok(first_remote.iter().zip(costs).map(|(n, c)| n * c).sum())
+}
+
+fn enter_code_deep(codes: &Vec<&str>, robots: usize) -> BigUint {
+    let (door, remote) = (Keypad::numeric(), Keypad::directional());
+
+    codes
+        .iter()
+        .map(|code| {
+            let presses =
+                deep_presses(&door, &remote, robots, code).expect("Puzzle keypads are valid");
+            complexity(code, presses)
+        })
+        .sum()
+}
+
+fn part1(codes: &Vec<&str>) -> i64 {
+    enter_code(codes, 2)
+}
+
+fn part2(codes: &Vec<&str>) -> i64 {
+    enter_code(codes, 25)
 }
 
 pub fn day21() {
@@ -129,11 +463,14 @@ pub fn day21() {
     let codes = input.lines().collect();
 
     println!("Part 1: {}", part1(&codes));
+    println!("Part 2: {}", part2(&codes));
+    println!("With 100 robots: {}", enter_code_deep(&codes, 100));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
     #[test]
     fn test_p1() {
         let input = "029A
@@ -155,7 +492,195 @@ mod tests {
 593A"
             .lines();
         let res = part1(&input.collect());
-        println!("{}", res);
-        assert!(res < 157596);
+        assert_eq!(155252, res);
+    }
+
+    #[test]
+    fn test_p2() {
+        let input = "208A
+586A
+341A
+463A
+593A"
+            .lines();
+        let res = part2(&input.collect());
+        assert_eq!(195664513288128, res);
+    }
+
+    #[test]
+    fn test_robot_levels() {
+        let lengths: Vec<_> = (0..3)
+            .map(|robots| {
+                KeypadChain::new(Keypad::numeric(), Keypad::directional(), robots)
+                    .unwrap()
+                    .presses("029A")
+            })
+            .collect();
+
+        // <A^A>^^AvvvA, v<<A>>^A<A>AvA<^AA>A<vAAA>^A and the 68 press sequence from the
+        // puzzle text
+        assert_eq!(vec![12, 28, 68], lengths);
+    }
+
+    #[test]
+    fn test_parse_keypad() {
+        let pad = Keypad::parse(
+            "#^A
+<v>",
+        )
+        .unwrap();
+        assert_eq!(Vec2::new(1, 0), pad.position('^'));
+        assert_eq!(Vec2::new(2, 1), pad.position('>'));
+        assert!(pad.is_bad(Vec2::new(0, 0)));
+        assert!(pad.is_bad(Vec2::new(3, 0)));
+    }
+
+    #[test]
+    fn test_custom_keypads() {
+        let phone = Keypad::parse(
+            "123
+456
+789
+*0A",
+        )
+        .unwrap();
+        let mut chain = KeypadChain::new(phone, Keypad::directional(), 0).unwrap();
+        assert_eq!(8, chain.presses("5A"));
+
+        // The only way from A to 1 goes around the gap
+        let detour = Keypad::parse(
+            "A#1
+234",
+        )
+        .unwrap();
+        let mut chain = KeypadChain::new(detour, Keypad::directional(), 0).unwrap();
+        assert_eq!(5, chain.presses("1"));
+
+        let qwerty = Keypad::parse(
+            "qwertyuiop
+ asdfghjkl
+  zxcvbnmA",
+        )
+        .unwrap();
+        let mut chain = KeypadChain::new(qwerty, Keypad::directional(), 0).unwrap();
+        assert_eq!(12 + 12, chain.presses("qA"));
+
+        // Extra buttons on the remotes never make the standard moves any cheaper
+        let large_remote = Keypad::parse(
+            "#^A
+<v>
+123",
+        )
+        .unwrap();
+        let mut chain = KeypadChain::new(Keypad::numeric(), large_remote, 2).unwrap();
+        assert_eq!(68, chain.presses("029A"));
+    }
+
+    #[test]
+    fn test_bad_keypads() {
+        assert_eq!(
+            Some(KeypadError::DuplicateButton('1')),
+            Keypad::parse("1#1A").err()
+        );
+
+        let remote = Keypad::directional();
+        let no_a = Keypad::parse("12").unwrap();
+        assert_eq!(
+            Some(KeypadError::MissingButton('A')),
+            KeypadChain::<i64>::new(no_a, remote.clone(), 1).err()
+        );
+        let no_down = Keypad::parse("#^A\n<#>").unwrap();
+        assert_eq!(
+            Some(KeypadError::MissingButton('v')),
+            KeypadChain::<i64>::new(Keypad::numeric(), no_down, 1).err()
+        );
+
+        // Buttons walled off by gaps can't be reached, whichever side the search starts on
+        let walled_door = Keypad::parse("A#1").unwrap();
+        assert!(matches!(
+            KeypadChain::<i64>::new(walled_door.clone(), remote.clone(), 1),
+            Err(KeypadError::Disconnected(_))
+        ));
+        let split_remote = Keypad::parse("^A\n##\n<v>").unwrap();
+        assert!(matches!(
+            KeypadChain::<i64>::new(Keypad::numeric(), split_remote, 1),
+            Err(KeypadError::Disconnected(_))
+        ));
+        assert!(deep_presses(&walled_door, &remote, 100, "1A").is_err());
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut chain: KeypadChain =
+            KeypadChain::new(Keypad::numeric(), Keypad::directional(), 2).unwrap();
+
+        for code in ["029A", "980A", "179A", "456A", "379A"] {
+            let sequences = chain.sequences(code);
+            assert_eq!(4, sequences.len());
+            assert_eq!(chain.presses(code), sequences[3].len() as i64);
+
+            let human = &sequences[3];
+            assert_eq!(Ok(code.to_string()), chain.replay(human));
+        }
+
+        let sequences = chain.sequences("029A");
+        assert_eq!(12, sequences[1].len());
+        assert_eq!(28, sequences[2].len());
+    }
+
+    #[test]
+    fn test_replay_gap() {
+        let chain: KeypadChain =
+            KeypadChain::new(Keypad::numeric(), Keypad::directional(), 0).unwrap();
+        assert_eq!(Ok("0".to_string()), chain.replay("<A"));
+        assert!(chain.replay("<<A").is_err());
+
+        let chain: KeypadChain =
+            KeypadChain::new(Keypad::numeric(), Keypad::directional(), 1).unwrap();
+        // Going around the gap of the remote is fine, going over it is not
+        assert_eq!(Ok(String::new()), chain.replay("v<<A"));
+        assert!(chain.replay("<<A").is_err());
+    }
+
+    #[test]
+    fn test_deep_chains() {
+        let codes = vec!["208A", "586A", "341A", "463A", "593A"];
+        assert_eq!(
+            BigUint::from(195664513288128u64),
+            enter_code_deep(&codes, 25)
+        );
+
+        let (door, remote) = (Keypad::numeric(), Keypad::directional());
+        let exact = KeypadChain::<u128>::new(door.clone(), remote.clone(), 60)
+            .unwrap()
+            .presses("029A");
+        assert_eq!(
+            BigUint::from(exact),
+            deep_presses(&door, &remote, 60, "029A").unwrap()
+        );
+
+        let deep = deep_presses(&door, &remote, 1000, "029A").unwrap();
+        assert!(deep.bits() > 1000);
+        assert_eq!(
+            KeypadChain::<BigUint>::new(door.clone(), remote.clone(), 1000)
+                .unwrap()
+                .presses("029A"),
+            deep
+        );
+        assert_eq!(
+            BigUint::from(enter_code::<u128>(&codes, 40)),
+            enter_code_deep(&codes, 40)
+        );
+        for code in ["029A", "980A", "179A", "456A", "379A"]
+            .iter()
+            .chain(&codes)
+        {
+            assert_eq!(
+                KeypadChain::<BigUint>::new(door.clone(), remote.clone(), 200)
+                    .unwrap()
+                    .presses(code),
+                deep_presses(&door, &remote, 200, code).unwrap()
+            );
+        }
     }
 }
@@ -1,7 +1,6 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fs,
-};
+use crate::util::bit_matrix::BitMatrix;
+use crate::util::bit_set::BitSet;
+use std::{collections::HashMap, fs};
 
 /*
 
@@ -43,61 +42,158 @@ fn next_secret(secret: i64) -> i64 {
     secret
 }
 
+const SECRET_BITS: usize = 24;
+
+/// `next_secret` as a bit matrix. Multiplying by 64 or 2048 and dividing by 32 are shifts,
+/// mixing is XOR and pruning drops the bits above 24, so every step is linear over GF(2).
+fn step_matrix() -> BitMatrix {
+    BitMatrix::from_linear(SECRET_BITS, |bits| next_secret(bits as i64) as u64)
+}
+
+/// The secret `steps` steps after `secret`, in `O(log steps)` matrix products.
+pub fn jump_ahead(secret: i64, steps: u64) -> i64 {
+    step_matrix().pow(steps).apply(secret as u64) as i64
+}
+
+/// The secret that `next_secret` turns into `secret`.
+pub fn previous_secret(secret: i64) -> i64 {
+    step_matrix()
+        .inverse()
+        .expect("Secret step is not invertible")
+        .apply(secret as u64) as i64
+}
+
 fn ones(n: i64) -> i64 {
     n % 10
 }
 
+const STEPS: usize = 2000;
+
 fn part1(secrets: &[i64]) -> i64 {
-    secrets
+    let jump = step_matrix().pow(STEPS as u64);
+    secrets.iter().map(|&s| jump.apply(s as u64) as i64).sum()
+}
+
+/// Price changes run from -9 to 9, so a sequence of four is a four digit base 19 number.
+const SEQUENCES: usize = 19 * 19 * 19 * 19;
+
+type Sequence = [i64; 4];
+
+fn sequence_index(sequence: Sequence) -> usize {
+    sequence
         .iter()
-        .map(|&s| {
-            let mut s = s;
-            for i in 0..2000 {
-                s = next_secret(s);
-            }
-            s as i64
+        .fold(0, |index, &delta| index * 19 + (delta + 9) as usize)
+}
+
+fn sequence_from_index(mut index: usize) -> Sequence {
+    let mut sequence = [0; 4];
+    for delta in sequence.iter_mut().rev() {
+        *delta = (index % 19) as i64 - 9;
+        index /= 19;
+    }
+    sequence
+}
+
+/// One secret number of a buyer, with the price it gives and the change from the last one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tick {
+    pub secret: i64,
+    pub price: i64,
+    pub delta: Option<i64>,
+}
+
+/// The initial secret followed by the next `steps` secrets of a buyer.
+pub fn timeline(secret: i64, steps: usize) -> Vec<Tick> {
+    let mut ticks = Vec::with_capacity(steps + 1);
+    ticks.push(Tick {
+        secret,
+        price: ones(secret),
+        delta: None,
+    });
+
+    let mut current = secret;
+    for _ in 0..steps {
+        let next = next_secret(current);
+        ticks.push(Tick {
+            secret: next,
+            price: ones(next),
+            delta: Some(ones(next) - ones(current)),
+        });
+        current = next;
+    }
+    ticks
+}
+
+/// Index of every sequence of four changes the buyer sees, with the price the monkey
+/// would sell at. Only the first time each sequence comes up counts. Kept small, since
+/// `top_sequences` holds on to the sales of every buyer.
+fn sales(secret: i64) -> Vec<(u32, u8)> {
+    let ticks = timeline(secret, STEPS);
+    let mut seen = BitSet::new(SEQUENCES);
+
+    ticks[1..]
+        .windows(4)
+        .filter_map(|window| {
+            let index = sequence_index([0, 1, 2, 3].map(|i| window[i].delta.unwrap()));
+            seen.insert(index)
+                .then(|| (index as u32, window[3].price as u8))
         })
-        .sum()
+        .collect()
 }
 
-fn part2(secrets: &[i64]) -> i64 {
-    let mut best_sequences = HashMap::new();
-    for &secret in secrets {
-        let mut sequences = HashSet::new();
-        let mut current = secret;
-        let mut d0 = None;
-        let mut d1 = None;
-        let mut d2 = None;
-        let mut d3 = None;
-        for _ in 0..2000 {
-            let next = next_secret(current);
-            let prev_ones = ones(current);
-            let current_ones = ones(next);
-            let delta = current_ones - prev_ones;
-            current = next;
-            d0 = d1;
-            d1 = d2;
-            d2 = d3;
-            d3 = Some(delta);
-            if d0.is_some() {
-                let key = (d0.unwrap(), d1.unwrap(), d2.unwrap(), d3.unwrap());
-                if !sequences.insert(key) {
-                    continue;
-                }
-
-                best_sequences
-                    .entry(key)
-                    .and_modify(|e: &mut Vec<i64>| e.push(current_ones))
-                    .or_insert(vec![current_ones]);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceReport {
+    pub sequence: Sequence,
+    pub bananas: i64,
+    /// Bananas from each buyer, in input order, 0 for buyers that never see the sequence
+    pub contributions: Vec<i64>,
+}
+
+/// The `k` sequences that earn the most bananas, best first. Ties go to the sequence
+/// with the smallest changes.
+pub fn top_sequences(secrets: &[i64], k: usize) -> Vec<SequenceReport> {
+    let sales: Vec<_> = secrets.iter().map(|&secret| sales(secret)).collect();
+    let mut totals = vec![0; SEQUENCES];
+    for &(index, price) in sales.iter().flatten() {
+        totals[index as usize] += price as i64;
+    }
+
+    let rank_key = |&index: &usize| (-totals[index], index);
+    // Only the top k need to be in order
+    let mut ranking: Vec<usize> = (0..SEQUENCES).collect();
+    if 0 < k && k < SEQUENCES {
+        ranking.select_nth_unstable_by_key(k - 1, rank_key);
+    }
+    ranking.truncate(k);
+    ranking.sort_by_key(rank_key);
+
+    let ranks: HashMap<usize, usize> = ranking
+        .iter()
+        .enumerate()
+        .map(|(rank, &index)| (index, rank))
+        .collect();
+    let mut contributions = vec![vec![0; secrets.len()]; ranking.len()];
+    for (buyer, sales) in sales.iter().enumerate() {
+        for &(index, price) in sales {
+            if let Some(&rank) = ranks.get(&(index as usize)) {
+                contributions[rank][buyer] = price as i64;
             }
         }
     }
 
-    best_sequences
-        .iter()
-        .map(|(_, v)| v.iter().sum())
-        .max()
-        .unwrap()
+    ranking
+        .into_iter()
+        .zip(contributions)
+        .map(|(index, contributions)| SequenceReport {
+            sequence: sequence_from_index(index),
+            bananas: totals[index],
+            contributions,
+        })
+        .collect()
+}
+
+fn part2(secrets: &[i64]) -> i64 {
+    top_sequences(secrets, 1)[0].bananas
 }
 
 fn parse_input(input: &str) -> Vec<i64> {
@@ -116,6 +212,14 @@ pub fn day22() {
     let secrets = parse_input(&input);
     println!("Part 1: {}", part1(&secrets));
     println!("Part 2: {}", part2(&secrets));
+
+    for report in top_sequences(&secrets, 5) {
+        let buyers = report.contributions.iter().filter(|&&c| c > 0).count();
+        println!(
+            "{:?}: {} bananas from {} buyers",
+            report.sequence, report.bananas, buyers
+        );
+    }
 }
 
 #[cfg(test)]
@@ -141,4 +245,97 @@ mod tests {
         let secrets = parse_input(&input);
         assert_eq!(23, part2(&secrets));
     }
+
+    #[test]
+    fn test_timeline() {
+        let ticks = timeline(123, 9);
+        assert_eq!(
+            vec![3, 0, 6, 5, 4, 4, 6, 4, 4, 2],
+            ticks.iter().map(|t| t.price).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                None,
+                Some(-3),
+                Some(6),
+                Some(-1),
+                Some(-1),
+                Some(0),
+                Some(2),
+                Some(-2),
+                Some(0),
+                Some(-2)
+            ],
+            ticks.iter().map(|t| t.delta).collect::<Vec<_>>()
+        );
+        assert_eq!(15887950, ticks[1].secret);
+        assert_eq!(7753432, ticks[9].secret);
+    }
+
+    #[test]
+    fn test_sequence_index() {
+        assert_eq!(0, sequence_index([-9, -9, -9, -9]));
+        assert_eq!(SEQUENCES - 1, sequence_index([9, 9, 9, 9]));
+        for index in [0, 1, 18, 19, 4242, SEQUENCES - 1] {
+            assert_eq!(index, sequence_index(sequence_from_index(index)));
+        }
+    }
+
+    #[test]
+    fn test_top_sequences() {
+        let secrets = [1, 2, 3, 2024];
+        let top = top_sequences(&secrets, 3);
+
+        assert_eq!(3, top.len());
+        assert_eq!([-2, 1, -1, 3], top[0].sequence);
+        assert_eq!(23, top[0].bananas);
+        assert_eq!(vec![7, 7, 0, 9], top[0].contributions);
+        for report in &top {
+            assert_eq!(report.bananas, report.contributions.iter().sum::<i64>());
+        }
+        assert!(top[1].bananas <= top[0].bananas && top[2].bananas <= top[1].bananas);
+
+        // Picking more sequences keeps the same order at the top
+        assert_eq!(top, top_sequences(&secrets, 50)[..3]);
+        assert_eq!(top[..1], top_sequences(&secrets, 1));
+        assert!(top_sequences(&secrets, 0).is_empty());
+    }
+
+    #[test]
+    fn test_jump_ahead() {
+        let mut secret = 123;
+        for steps in 0..50 {
+            assert_eq!(secret, jump_ahead(123, steps));
+            secret = next_secret(secret);
+        }
+
+        let mut secret = 2024;
+        for _ in 0..2000 {
+            secret = next_secret(secret);
+        }
+        assert_eq!(8667524, secret);
+        assert_eq!(secret, jump_ahead(2024, 2000));
+
+        // Every non-zero secret lies on a single cycle through all 2^24 - 1 of them: the
+        // period of 123 divides 2^24 - 1 = 3^2 * 5 * 7 * 13 * 17 * 241, and no proper
+        // divisor will do, since each one divides (2^24 - 1) / p for a prime factor p
+        let full = (1 << 24) - 1;
+        assert_eq!(123, jump_ahead(123, full));
+        for p in [3, 5, 7, 13, 17, 241] {
+            assert_ne!(123, jump_ahead(123, full / p));
+        }
+        assert_eq!(0, jump_ahead(0, 12345));
+    }
+
+    #[test]
+    fn test_previous_secret() {
+        for secret in [1, 123, 2024, 16777215] {
+            assert_eq!(secret, previous_secret(next_secret(secret)));
+            assert_eq!(secret, next_secret(previous_secret(secret)));
+        }
+        assert_eq!(123, previous_secret(15887950));
+
+        let back = step_matrix().inverse().unwrap().pow(2000);
+        assert_eq!(2024, back.apply(8667524) as i64);
+    }
 }
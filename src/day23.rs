@@ -1,5 +1,6 @@
+use regex::Regex;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs,
 };
 
@@ -12,89 +13,560 @@ fn parse_input(input: &str) -> Vec<Link> {
         .collect()
 }
 
-fn part1(links: &Vec<Link>) -> i32 {
-    let mut link_map = HashMap::new();
-    let mut link_set = HashSet::new();
-    for (link_0, link_1) in links {
-        link_set.insert(link_0);
-        link_set.insert(link_1);
-        link_map
-            .entry(link_0)
-            .and_modify(|l: &mut Vec<&str>| l.push(link_1))
-            .or_insert(vec![link_1]);
-        link_map
-            .entry(link_1)
-            .and_modify(|l| l.push(link_0))
-            .or_insert(vec![link_0]);
-    }
-
-    let mut truples = HashSet::new();
-
-    for link in link_set {
-        let nodes = link_map.get(link).unwrap();
-        for i in 0..nodes.len() - 1 {
-            for j in i + 1..nodes.len() {
-                let link_0 = &nodes[i];
-                let link_1 = &nodes[j];
-                if link_map.get(link_0).unwrap().contains(&link_1) {
-                    let mut links = [link, link_0, link_1];
-                    links.sort();
-                    truples.insert(links);
+/// Undirected graph of computers, with each name interned to an id in order of first
+/// appearance.
+pub struct Graph<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+    adjacency: Vec<BTreeSet<usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DegreeStats {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    /// Number of nodes with each degree
+    pub counts: BTreeMap<usize, usize>,
+}
+
+impl<'a> Graph<'a> {
+    /// Fails on a link from a node to itself, like the other importers.
+    pub fn new(links: &[Link<'a>]) -> Result<Graph<'a>, String> {
+        let mut graph = Graph::empty();
+        for &(a, b) in links {
+            graph.add_edge(a, b)?;
+        }
+        Ok(graph)
+    }
+
+    fn empty() -> Graph<'a> {
+        Graph {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            adjacency: Vec::new(),
+        }
+    }
+
+    pub fn add_node(&mut self, name: &'a str) -> usize {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.adjacency.push(BTreeSet::new());
+            self.names.len() - 1
+        })
+    }
+
+    /// A link from a node to itself is an error, since a node that is its own neighbour
+    /// would break the clique searches.
+    pub fn add_edge(&mut self, a: &'a str, b: &'a str) -> Result<(), String> {
+        if a == b {
+            return Err(format!("Link {a}-{a} is a self-loop"));
+        }
+        let (a, b) = (self.add_node(a), self.add_node(b));
+        self.adjacency[a].insert(b);
+        self.adjacency[b].insert(a);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.adjacency[a].contains(&b)
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.adjacency.iter().map(|n| n.len()).sum::<usize>() / 2
+    }
+
+    /// Names of the given nodes in alphabetical order.
+    pub fn names(&self, ids: &[usize]) -> Vec<&'a str> {
+        let mut names: Vec<&str> = ids.iter().map(|&id| self.names[id]).collect();
+        names.sort();
+        names
+    }
+
+    /// Largest set of nodes that are all linked to each other, found by Bron-Kerbosch
+    /// with pivoting.
+    pub fn max_clique(&self) -> Vec<usize> {
+        let mut best = Vec::new();
+        self.bron_kerbosch(
+            &mut Vec::new(),
+            (0..self.len()).collect(),
+            BTreeSet::new(),
+            &mut best,
+        );
+        best
+    }
+
+    /// Grows the clique `r` with nodes from `p`, skipping the ones in `x` that an earlier
+    /// branch already tried. Only neighbours of the pivot can be left out of a maximal
+    /// clique containing it, so those are the only branches that need exploring.
+    fn bron_kerbosch(
+        &self,
+        r: &mut Vec<usize>,
+        mut p: BTreeSet<usize>,
+        mut x: BTreeSet<usize>,
+        best: &mut Vec<usize>,
+    ) {
+        if p.is_empty() && x.is_empty() {
+            if r.len() > best.len() {
+                *best = r.clone();
+            }
+            return;
+        }
+        if r.len() + p.len() <= best.len() {
+            return;
+        }
+
+        let pivot = p
+            .union(&x)
+            .max_by_key(|&&u| p.intersection(&self.adjacency[u]).count())
+            .copied()
+            .unwrap();
+        let candidates: Vec<usize> = p.difference(&self.adjacency[pivot]).copied().collect();
+
+        for v in candidates {
+            let neighbours = &self.adjacency[v];
+            r.push(v);
+            self.bron_kerbosch(
+                r,
+                p.intersection(neighbours).copied().collect(),
+                x.intersection(neighbours).copied().collect(),
+                best,
+            );
+            r.pop();
+            p.remove(&v);
+            x.insert(v);
+        }
+    }
+
+    /// Every clique of exactly `k` nodes whose names pass `filter`, each listed once in
+    /// increasing id order.
+    pub fn cliques(&self, k: usize, filter: impl Fn(&[&str]) -> bool) -> Vec<Vec<usize>> {
+        let mut res = Vec::new();
+        let mut clique = Vec::with_capacity(k);
+        for start in 0..self.len() {
+            clique.push(start);
+            self.extend_clique(&mut clique, k, &filter, &mut res);
+            clique.pop();
+        }
+        res
+    }
+
+    fn extend_clique(
+        &self,
+        clique: &mut Vec<usize>,
+        k: usize,
+        filter: &impl Fn(&[&str]) -> bool,
+        res: &mut Vec<Vec<usize>>,
+    ) {
+        if clique.len() == k {
+            let names: Vec<&str> = clique.iter().map(|&id| self.names[id]).collect();
+            if filter(&names) {
+                res.push(clique.clone());
+            }
+            return;
+        }
+
+        let last = *clique.last().unwrap();
+        for &next in self.adjacency[last].range(last + 1..) {
+            if clique.iter().all(|&id| self.has_edge(id, next)) {
+                clique.push(next);
+                self.extend_clique(clique, k, filter, res);
+                clique.pop();
+            }
+        }
+    }
+
+    /// Groups of nodes that can reach each other, largest first.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut component = vec![None; self.len()];
+        let mut res: Vec<Vec<usize>> = Vec::new();
+
+        for start in 0..self.len() {
+            if component[start].is_some() {
+                continue;
+            }
+            let mut members = vec![start];
+            component[start] = Some(res.len());
+            let mut i = 0;
+            while i < members.len() {
+                for &next in &self.adjacency[members[i]] {
+                    if component[next].is_none() {
+                        component[next] = Some(res.len());
+                        members.push(next);
+                    }
                 }
+                i += 1;
             }
+            members.sort();
+            res.push(members);
         }
+
+        res.sort_by_key(|members| std::cmp::Reverse(members.len()));
+        res
     }
 
-    truples
-        .iter()
-        .filter(|t| t.iter().any(|l| l.starts_with("t")))
-        .count() as i32
-}
+    pub fn degree_stats(&self) -> DegreeStats {
+        let mut counts = BTreeMap::new();
+        for neighbours in &self.adjacency {
+            *counts.entry(neighbours.len()).or_insert(0) += 1;
+        }
 
-fn part2(links: &[Link]) -> String {
-    let mut link_map = HashMap::new();
-    let mut link_set = HashSet::new();
-    for (link_0, link_1) in links {
-        link_set.insert(link_0);
-        link_set.insert(link_1);
-        link_map
-            .entry(link_0)
-            .and_modify(|l: &mut Vec<&str>| l.push(link_1))
-            .or_insert(vec![link_1]);
-        link_map
-            .entry(link_1)
-            .and_modify(|l| l.push(link_0))
-            .or_insert(vec![link_0]);
-    }
-
-    let mut largest_set = Vec::new();
-
-    for link in link_set {
-        let nodes = link_map.get(link).unwrap();
-        let mut largest_link_sets: Vec<HashSet<&&str>> = Vec::new();
-
-        for node in nodes {
-            for ls in &mut largest_link_sets {
-                let node_links = link_map.get(node).unwrap();
-                if !ls.iter().all(|l| node_links.contains(l)) {
-                    continue;
+        DegreeStats {
+            min: counts.keys().next().copied().unwrap_or(0),
+            max: counts.keys().next_back().copied().unwrap_or(0),
+            mean: if self.len() == 0 {
+                0.0
+            } else {
+                2.0 * self.edge_count() as f64 / self.len() as f64
+            },
+            counts,
+        }
+    }
+
+    /// Graphviz `graph` with every node and link, drawing the `highlight` nodes and the
+    /// links between them in red.
+    pub fn to_dot(&self, highlight: &[usize]) -> String {
+        let highlight: HashSet<usize> = highlight.iter().copied().collect();
+        let mut res = String::from("graph lan {\n    node [shape=circle];\n");
+
+        for (id, name) in self.names.iter().enumerate() {
+            if highlight.contains(&id) {
+                res += &format!("    {} [style=filled, fillcolor=red];\n", quote_dot(name));
+            } else {
+                res += &format!("    {};\n", quote_dot(name));
+            }
+        }
+
+        for (a, neighbours) in self.adjacency.iter().enumerate() {
+            for &b in neighbours.range(a + 1..) {
+                let style = if highlight.contains(&a) && highlight.contains(&b) {
+                    " [color=red, penwidth=3]"
+                } else {
+                    ""
+                };
+                res += &format!(
+                    "    {} -- {}{style};\n",
+                    quote_dot(self.names[a]),
+                    quote_dot(self.names[b])
+                );
+            }
+        }
+
+        res += "}\n";
+        res
+    }
+
+    /// Reads the node and link statements of an undirected Graphviz graph, including
+    /// chains like `a -- b -- c` and subgraphs, whose nodes and links are simply added
+    /// to the graph. Attributes, ports and `node`/`edge`/`graph` defaults are ignored.
+    /// Names are borrowed from the input, so quoted ids with escapes like `\"` are
+    /// rejected rather than unescaped.
+    pub fn from_dot(input: &'a str) -> Result<Graph<'a>, String> {
+        let tokens = dot_tokens(input)?;
+        let mut graph = Graph::empty();
+        let mut i = 0;
+
+        while i < tokens.len() {
+            match tokens[i] {
+                DotToken::Punct(";" | "{" | "}") => i += 1,
+                DotToken::Id("strict", false) => i += 1,
+                // `graph G {`, `subgraph G {` and the bare `{` of an anonymous subgraph
+                DotToken::Id("graph" | "subgraph", false)
+                    if tokens.get(i + 1) != Some(&DotToken::Punct("[")) =>
+                {
+                    i += 1;
+                    if let Some(DotToken::Id(..)) = tokens.get(i) {
+                        i += 1;
+                    }
+                }
+                DotToken::Id("digraph", false) => {
+                    return Err("Only undirected DOT graphs are supported".to_string())
+                }
+                DotToken::Id("node" | "edge" | "graph", false) => {
+                    i = skip_dot_attributes(&tokens, i + 1)?;
+                }
+                // `rankdir = LR` and the like
+                DotToken::Id(..) if tokens.get(i + 1) == Some(&DotToken::Punct("=")) => i += 3,
+                DotToken::Id(..) => {
+                    let mut from = dot_name(&tokens[i])?;
+                    i = skip_dot_port(&tokens, i + 1);
+                    let mut linked = false;
+                    while let Some(DotToken::Punct(op @ ("--" | "->"))) = tokens.get(i) {
+                        if *op == "->" {
+                            return Err(format!("Directed link from `{from}` in DOT graph"));
+                        }
+                        let to = tokens
+                            .get(i + 1)
+                            .ok_or(format!("DOT link from `{from}` has no end"))
+                            .and_then(dot_name)?;
+                        graph.add_edge(from, to)?;
+                        linked = true;
+                        from = to;
+                        i = skip_dot_port(&tokens, i + 2);
+                    }
+                    if !linked {
+                        graph.add_node(from);
+                    }
+                    i = skip_dot_attributes(&tokens, i)?;
+                }
+                DotToken::Punct(punct) => {
+                    return Err(format!("Unexpected `{punct}` in DOT graph"));
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut res = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <graph id=\"lan\" edgedefault=\"undirected\">\n"
+        ));
+
+        for name in &self.names {
+            res += &format!("    <node id=\"{}\"/>\n", escape_xml(name));
+        }
+        for (a, neighbours) in self.adjacency.iter().enumerate() {
+            for &b in neighbours.range(a + 1..) {
+                res += &format!(
+                    "    <edge source=\"{}\" target=\"{}\"/>\n",
+                    escape_xml(self.names[a]),
+                    escape_xml(self.names[b])
+                );
+            }
+        }
+
+        res += "  </graph>\n</graphml>\n";
+        res
+    }
+
+    /// Reads the `node` and `edge` elements of a GraphML document. Edges are treated as
+    /// undirected whatever the document says. Names are borrowed from the document, so
+    /// ids with entity references like `&amp;` are rejected rather than unescaped.
+    pub fn from_graphml(input: &'a str) -> Result<Graph<'a>, String> {
+        let element = Regex::new(r"<(node|edge)\b([^>]*)>").expect("Bad regex");
+        let attribute = Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).expect("Bad regex");
+        let mut graph = Graph::empty();
+
+        for caps in element.captures_iter(input) {
+            let (tag, attributes) = (caps.get(1).unwrap(), caps.get(2).unwrap().as_str());
+            let attributes: HashMap<&str, &str> = attribute
+                .captures_iter(attributes)
+                .map(|a| (a.get(1).unwrap().as_str(), a.get(2).unwrap().as_str()))
+                .collect();
+            let get = |key: &str| match attributes.get(key) {
+                Some(value) if value.contains('&') => {
+                    Err(format!("GraphML {key} `{value}` has an entity reference"))
                 }
-                ls.insert(node);
+                Some(value) => Ok(*value),
+                None => Err(format!("GraphML {} without `{key}`", tag.as_str())),
+            };
+
+            if tag.as_str() == "node" {
+                graph.add_node(get("id")?);
+            } else {
+                graph.add_edge(get("source")?, get("target")?)?;
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Square 0/1 matrix with a header row and column of names.
+    pub fn to_adjacency_csv(&self) -> String {
+        let mut res = format!(",{}\n", self.names.join(","));
+        for (a, name) in self.names.iter().enumerate() {
+            let row: Vec<&str> = (0..self.len())
+                .map(|b| if self.has_edge(a, b) { "1" } else { "0" })
+                .collect();
+            res += &format!("{name},{}\n", row.join(","));
+        }
+        res
+    }
+
+    pub fn from_adjacency_csv(input: &'a str) -> Result<Graph<'a>, String> {
+        let mut lines = input.lines().map(str::trim).filter(|l| !l.is_empty());
+        let header: Vec<&str> = lines
+            .next()
+            .ok_or("Empty adjacency matrix")?
+            .split(',')
+            .skip(1)
+            .collect();
+
+        let mut graph = Graph::empty();
+        for name in &header {
+            graph.add_node(name);
+        }
+
+        let mut links = HashSet::new();
+        for line in lines {
+            let mut cells = line.split(',');
+            let a = cells.next().unwrap();
+            let row: Vec<&str> = cells.collect();
+            if row.len() != header.len() {
+                return Err(format!(
+                    "Row `{a}` has {} cells, expected {}",
+                    row.len(),
+                    header.len()
+                ));
+            }
+
+            for (&b, cell) in header.iter().zip(row) {
+                match cell {
+                    "0" => {}
+                    "1" => {
+                        links.insert((a, b));
+                    }
+                    _ => return Err(format!("Bad cell `{cell}` in row `{a}`")),
+                }
+            }
+        }
+
+        for &(a, b) in &links {
+            if !links.contains(&(b, a)) {
+                return Err(format!("Link {a}-{b} is missing its reverse"));
             }
-            let mut this_cluster = HashSet::new();
-            this_cluster.insert(link);
-            this_cluster.insert(node);
-            largest_link_sets.push(this_cluster);
+            graph.add_edge(a, b)?;
         }
 
-        let largest = largest_link_sets.iter().max_by_key(|n| n.len()).unwrap();
-        if largest.len() > largest_set.len() {
-            largest_set = largest.iter().map(|&&s| s).collect();
+        Ok(graph)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DotToken<'a> {
+    /// Bare or quoted id, and whether it is quoted with escapes in it
+    Id(&'a str, bool),
+    Punct(&'a str),
+}
+
+/// Splits DOT source into ids and punctuation, dropping whitespace and comments.
+fn dot_tokens(input: &str) -> Result<Vec<DotToken<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+
+        let len = if rest.starts_with("//") || rest.starts_with('#') {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            rest.find("*/").ok_or("Unclosed DOT comment")? + 2
+        } else if rest.starts_with("--") || rest.starts_with("->") {
+            tokens.push(DotToken::Punct(&rest[..2]));
+            2
+        } else if "{}[];,=:".contains(c) {
+            tokens.push(DotToken::Punct(&rest[..1]));
+            1
+        } else if c == '"' {
+            let mut escaped = false;
+            let mut end = None;
+            let mut chars = rest.char_indices().skip(1);
+            while let Some((j, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        escaped = true;
+                        chars.next();
+                    }
+                    '"' => {
+                        end = Some(j);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let end = end.ok_or("Unclosed DOT string")?;
+            tokens.push(DotToken::Id(&rest[1..end], escaped));
+            end + 1
+        } else {
+            // Names and numerals, which may start with a minus sign
+            let len = rest
+                .char_indices()
+                .find(|&(j, c)| {
+                    !(c.is_alphanumeric() || c == '_' || c == '.' || (j == 0 && c == '-'))
+                })
+                .map_or(rest.len(), |(j, _)| j);
+            if len == 0 {
+                return Err(format!("Unexpected `{c}` in DOT graph"));
+            }
+            tokens.push(DotToken::Id(&rest[..len], false));
+            len
+        };
+        rest = &rest[len..];
+    }
+}
+
+fn dot_name<'a>(token: &DotToken<'a>) -> Result<&'a str, String> {
+    match *token {
+        DotToken::Id(name, false) => Ok(name),
+        DotToken::Id(name, true) => Err(format!("DOT id `{name}` has an escape sequence")),
+        DotToken::Punct(punct) => Err(format!("Expected a DOT id, found `{punct}`")),
+    }
+}
+
+/// Index past the `:port` or `:port:compass` after a node id at `i - 1`.
+fn skip_dot_port(tokens: &[DotToken], mut i: usize) -> usize {
+    while tokens.get(i) == Some(&DotToken::Punct(":")) {
+        i += 2;
+    }
+    i
+}
+
+/// Index past any `[...]` attribute lists starting at `i`.
+fn skip_dot_attributes(tokens: &[DotToken], mut i: usize) -> Result<usize, String> {
+    while tokens.get(i) == Some(&DotToken::Punct("[")) {
+        i += tokens[i..]
+            .iter()
+            .position(|t| *t == DotToken::Punct("]"))
+            .ok_or("Unclosed DOT attribute list")?
+            + 1;
+    }
+    Ok(i)
+}
+
+/// Quotes a name as a DOT id.
+fn quote_dot(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Escapes text for an XML attribute value in double quotes.
+fn escape_xml(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => res += "&amp;",
+            '<' => res += "&lt;",
+            '>' => res += "&gt;",
+            '"' => res += "&quot;",
+            _ => res.push(c),
         }
     }
+    res
+}
 
-    largest_set.sort();
-    largest_set.join(",")
+fn part1(links: &[Link]) -> i32 {
+    Graph::new(links)
+        .expect("Puzzle links are not self-loops")
+        .cliques(3, |names| names.iter().any(|n| n.starts_with('t')))
+        .len() as i32
+}
+
+fn part2(links: &[Link]) -> String {
+    let graph = Graph::new(links).expect("Puzzle links are not self-loops");
+    graph.names(&graph.max_clique()).join(",")
 }
 
 pub fn day23() {
@@ -103,6 +575,18 @@ pub fn day23() {
 
     println!("Part 1: {}", part1(&links));
     println!("Part 2: {}", part2(&links));
+
+    let graph = Graph::new(&links).expect("Puzzle links are not self-loops");
+    let stats = graph.degree_stats();
+    println!(
+        "{} computers, {} links, {} components, degree {}..={} (mean {:.2})",
+        graph.len(),
+        graph.edge_count(),
+        graph.components().len(),
+        stats.min,
+        stats.max,
+        stats.mean
+    );
 }
 
 #[cfg(test)]
@@ -186,4 +670,219 @@ mod tests {
         let links = parse_input(&input);
         assert_eq!("co,de,ka,ta", part2(&links));
     }
+
+    const SAMPLE: &str = "kh-tc
+    qp-kh
+    de-cg
+    ka-co
+    yn-aq
+    qp-ub
+    cg-tb
+    vc-aq
+    tb-ka
+    wh-tc
+    yn-cg
+    kh-ub
+    ta-co
+    de-co
+    tc-td
+    tb-wq
+    wh-td
+    ta-ka
+    td-qp
+    aq-cg
+    wq-ub
+    ub-vc
+    de-ta
+    wq-aq
+    wq-vc
+    wh-yn
+    ka-de
+    kh-ta
+    co-tc
+    wh-qp
+    tb-vc
+    td-yn";
+
+    #[test]
+    fn test_cliques() {
+        let links = parse_input(SAMPLE);
+        let graph = Graph::new(&links).unwrap();
+
+        assert_eq!(12, graph.cliques(3, |_| true).len());
+        let with_t = graph.cliques(3, |names| names.iter().any(|n| n.starts_with('t')));
+        assert_eq!(7, with_t.len());
+        assert!(with_t
+            .iter()
+            .any(|c| graph.names(c) == vec!["co", "de", "ta"]));
+
+        let quads = graph.cliques(4, |_| true);
+        assert_eq!(1, quads.len());
+        assert_eq!(vec!["co", "de", "ka", "ta"], graph.names(&quads[0]));
+        assert!(graph.cliques(5, |_| true).is_empty());
+        assert_eq!(16, graph.cliques(1, |_| true).len());
+        assert_eq!(graph.edge_count(), graph.cliques(2, |_| true).len());
+    }
+
+    #[test]
+    fn test_max_clique() {
+        // Greedily growing from a first would pick up b and then get stuck, missing c-d-e-f
+        let links = parse_input(
+            "a-b
+            a-c
+            a-d
+            c-d
+            c-e
+            c-f
+            d-e
+            d-f
+            e-f",
+        );
+        let graph = Graph::new(&links).unwrap();
+        assert_eq!(vec!["c", "d", "e", "f"], graph.names(&graph.max_clique()));
+        assert_eq!(vec!["a"], graph.names(&[graph.id("a").unwrap()]));
+
+        let empty = Graph::new(&[]).unwrap();
+        assert!(empty.max_clique().is_empty());
+    }
+
+    #[test]
+    fn test_components_and_degrees() {
+        let mut links = parse_input(SAMPLE);
+        links.extend(parse_input("x1-x2\nx2-x3"));
+        let graph = Graph::new(&links).unwrap();
+
+        let components = graph.components();
+        assert_eq!(
+            vec![16, 3],
+            components.iter().map(|c| c.len()).collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["x1", "x2", "x3"], graph.names(&components[1]));
+
+        let stats = graph.degree_stats();
+        assert_eq!(1, stats.min);
+        assert_eq!(4, stats.max);
+        assert_eq!(Some(&16), stats.counts.get(&4));
+        assert_eq!(Some(&2), stats.counts.get(&1));
+        assert!((stats.mean - 68.0 / 19.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dot() {
+        let links = parse_input(SAMPLE);
+        let graph = Graph::new(&links).unwrap();
+        let dot = graph.to_dot(&graph.max_clique());
+
+        assert!(dot.starts_with("graph lan {"));
+        assert!(dot.contains("\"co\" [style=filled, fillcolor=red];"));
+        assert!(dot.contains("\"kh\";"));
+        assert!(dot.contains("\"ka\" -- \"co\" [color=red, penwidth=3];"));
+        assert!(dot.contains("\"kh\" -- \"tc\";"));
+        assert_eq!(32, dot.matches(" -- ").count());
+
+        let loaded = Graph::from_dot(&dot).unwrap();
+        assert_eq!(dot, loaded.to_dot(&loaded.max_clique()));
+
+        let handwritten = Graph::from_dot(
+            "strict graph {
+                node [shape=box];
+                rankdir = LR;
+                a -- b [label=\"x;y\"];
+                \"b\" -- c:n
+                d; /* e -- f */
+            }",
+        )
+        .unwrap();
+        assert_eq!(4, handwritten.len());
+        assert_eq!(2, handwritten.edge_count());
+
+        let one_line = Graph::from_dot("graph G { a -- b; b -- c; subgraph { c -- d } }").unwrap();
+        assert_eq!(vec!["a", "b", "c", "d"], one_line.names(&[0, 1, 2, 3]));
+        assert_eq!(3, one_line.edge_count());
+        let chain = Graph::from_dot("graph { a -- b -- c; -1.5 -- a }").unwrap();
+        assert!(chain.has_edge(0, 1) && chain.has_edge(1, 2) && !chain.has_edge(0, 2));
+        assert_eq!(Some(3), chain.id("-1.5"));
+        assert!(Graph::from_dot("a -> b;").is_err());
+        assert!(Graph::from_dot("digraph { a; }").is_err());
+        assert!(Graph::from_dot("graph { a -- }").is_err());
+        assert!(Graph::from_dot("graph { \"a").is_err());
+
+        // Names that aren't plain words survive the round trip, while escaped quotes and
+        // backslashes are written out but can't be borrowed back
+        let odd_names = Graph::new(&[("a.b", "c d"), ("c d", "e-f"), ("e-f", "1x")]).unwrap();
+        let dot = odd_names.to_dot(&[]);
+        assert!(dot.contains("\"a.b\" -- \"c d\";"));
+        let loaded = Graph::from_dot(&dot).unwrap();
+        assert_eq!(odd_names.to_adjacency_csv(), loaded.to_adjacency_csv());
+        let escaped = Graph::new(&[("a\"b", "c\\d")]).unwrap().to_dot(&[]);
+        assert!(escaped.contains("\"a\\\"b\" -- \"c\\\\d\";"));
+        assert!(Graph::from_dot(&escaped).is_err());
+    }
+
+    #[test]
+    fn test_graphml() {
+        let links = parse_input(SAMPLE);
+        let graph = Graph::new(&links).unwrap();
+        let graphml = graph.to_graphml();
+
+        assert!(graphml.contains("<node id=\"kh\"/>"));
+        assert!(graphml.contains("<edge source=\"kh\" target=\"tc\"/>"));
+
+        let loaded = Graph::from_graphml(&graphml).unwrap();
+        assert_eq!(graphml, loaded.to_graphml());
+        assert_eq!(
+            vec!["co", "de", "ka", "ta"],
+            loaded.names(&loaded.max_clique())
+        );
+
+        let reordered =
+            Graph::from_graphml("<edge target=\"b\" id=\"e0\" source=\"a\"></edge>").unwrap();
+        assert!(reordered.has_edge(0, 1));
+        assert!(Graph::from_graphml("<edge source=\"a\"/>").is_err());
+
+        let odd_names = Graph::new(&[("a&b", "<c>"), ("<c>", "d\"e")]).unwrap();
+        let graphml = odd_names.to_graphml();
+        assert!(graphml.contains("<node id=\"a&amp;b\"/>"));
+        assert!(graphml.contains("<edge source=\"&lt;c&gt;\" target=\"d&quot;e\"/>"));
+        assert!(Graph::from_graphml(&graphml).is_err());
+    }
+
+    #[test]
+    fn test_adjacency_csv() {
+        let links = parse_input("a-b\nb-c");
+        let graph = Graph::new(&links).unwrap();
+        let csv = graph.to_adjacency_csv();
+        assert_eq!(",a,b,c\na,0,1,0\nb,1,0,1\nc,0,1,0\n", csv);
+
+        let loaded = Graph::from_adjacency_csv(&csv).unwrap();
+        assert_eq!(csv, loaded.to_adjacency_csv());
+
+        let sample = Graph::new(&parse_input(SAMPLE)).unwrap();
+        let csv = sample.to_adjacency_csv();
+        assert_eq!(
+            csv,
+            Graph::from_adjacency_csv(&csv).unwrap().to_adjacency_csv()
+        );
+
+        assert!(Graph::from_adjacency_csv(",a,b\na,0,1\nb,0,0").is_err());
+        assert!(Graph::from_adjacency_csv(",a,b\na,0,1\nb,1").is_err());
+        assert!(Graph::from_adjacency_csv(",a,b\na,0,2\nb,2,0").is_err());
+    }
+
+    #[test]
+    fn test_self_links() {
+        // Every importer refuses a node linked to itself
+        assert!(Graph::new(&parse_input("a-a\na-b")).is_err());
+        assert!(Graph::from_dot("graph { a -- b; a -- a; }").is_err());
+        assert!(Graph::from_dot("graph { b -- a -- a }").is_err());
+        assert!(Graph::from_graphml("<edge source=\"a\" target=\"a\"/>").is_err());
+        assert!(Graph::from_adjacency_csv(",a,b\na,1,1\nb,1,0").is_err());
+
+        let mut graph = Graph::new(&[("a", "b")]).unwrap();
+        assert_eq!(
+            Err("Link b-b is a self-loop".to_string()),
+            graph.add_edge("b", "b")
+        );
+        assert_eq!(1, graph.edge_count());
+    }
 }
@@ -4,13 +4,20 @@ use std::{
     fs,
 };
 
-use regex::Regex;
+use num_bigint::BigUint;
 
-#[derive(Clone, Copy, Debug)]
+use crate::util::rng::Rng;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum Operation {
     And,
     Or,
     Xor,
+    Nand,
+    Nor,
+    Xnor,
+    Not,
+    Const(bool),
 }
 
 impl From<&str> for Operation {
@@ -19,6 +26,12 @@ impl From<&str> for Operation {
             "AND" => Operation::And,
             "OR" => Operation::Or,
             "XOR" => Operation::Xor,
+            "NAND" => Operation::Nand,
+            "NOR" => Operation::Nor,
+            "XNOR" => Operation::Xnor,
+            "NOT" => Operation::Not,
+            "0" => Operation::Const(false),
+            "1" => Operation::Const(true),
             _ => panic!("Invalid operation name"),
         }
     }
@@ -30,10 +43,62 @@ impl Display for Operation {
             Operation::And => write!(f, "&"),
             Operation::Or => write!(f, "|"),
             Operation::Xor => write!(f, "^"),
+            Operation::Nand => write!(f, "~&"),
+            Operation::Nor => write!(f, "~|"),
+            Operation::Xnor => write!(f, "~^"),
+            Operation::Not => write!(f, "~"),
+            Operation::Const(false) => write!(f, "0"),
+            Operation::Const(true) => write!(f, "1"),
         }
     }
 }
 
+impl Operation {
+    /// The keyword used for this operation in gate lists.
+    fn name(self) -> &'static str {
+        match self {
+            Operation::And => "AND",
+            Operation::Or => "OR",
+            Operation::Xor => "XOR",
+            Operation::Nand => "NAND",
+            Operation::Nor => "NOR",
+            Operation::Xnor => "XNOR",
+            Operation::Not => "NOT",
+            Operation::Const(false) => "0",
+            Operation::Const(true) => "1",
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Operation::Const(_) => 0,
+            Operation::Not => 1,
+            _ => 2,
+        }
+    }
+
+    fn apply(self, left: u64, right: u64) -> u64 {
+        match self {
+            Operation::And => left & right,
+            Operation::Or => left | right,
+            Operation::Xor => left ^ right,
+            Operation::Nand => !(left & right),
+            Operation::Nor => !(left | right),
+            Operation::Xnor => !(left ^ right),
+            Operation::Not => !left,
+            Operation::Const(value) => {
+                if value {
+                    !0
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+/// A gate driving `name`. NOT gates only read `left`, and constants read neither input,
+/// in which case the unused inputs are empty.
 #[derive(Clone, Copy, Debug)]
 struct Node<'a> {
     name: &'a str,
@@ -42,6 +107,12 @@ struct Node<'a> {
     right: &'a str,
 }
 
+impl<'a> Node<'a> {
+    fn inputs(&self) -> impl Iterator<Item = &'a str> {
+        [self.left, self.right].into_iter().take(self.op.arity())
+    }
+}
+
 type NodeValues<'a> = HashMap<&'a str, bool>;
 
 fn parse_input(input: &str) -> (Vec<Node>, NodeValues) {
@@ -54,94 +125,290 @@ fn parse_input(input: &str) -> (Vec<Node>, NodeValues) {
             .map(|(id, value)| (id, if value.trim() == "0" { false } else { true })),
     );
 
-    let node_re = Regex::new(r"(\S+) (AND|OR|XOR) (\S+) -> (\S+)").expect("Bad regex");
-
     let nodes = nodes_list
         .lines()
-        .filter_map(|l| node_re.captures(l))
-        .map(|r| r.extract())
-        .map(|(_, [left, op, right, name])| Node {
-            name,
-            left,
-            right,
-            op: op.into(),
+        .filter_map(|l| l.split_once(" -> "))
+        .map(|(gate, name)| {
+            let tokens: Vec<_> = gate.split_whitespace().collect();
+            let (op, left, right) = match tokens[..] {
+                [left, op, right] => (op, left, right),
+                [op, input] => (op, input, ""),
+                [value] => (value, "", ""),
+                _ => panic!("Invalid gate {gate}"),
+            };
+            Node {
+                name: name.trim(),
+                op: op.into(),
+                left,
+                right,
+            }
         })
         .collect();
 
     (nodes, node_values)
 }
 
-fn resolve_values<'a>(nodes: &'a Vec<Node>, values: &'a NodeValues) -> NodeValues<'a> {
-    let mut values = values.clone();
+/// Only the first `op.arity()` inputs are connected.
+#[derive(Clone, Copy, Debug)]
+struct Gate {
+    op: Operation,
+    inputs: [usize; 2],
+}
+
+impl Gate {
+    fn inputs(&self) -> &[usize] {
+        &self.inputs[..self.op.arity()]
+    }
+}
 
-    let mut queue = nodes.clone();
-    let nodes: HashMap<&str, Node> = HashMap::from_iter(nodes.iter().map(|n| (n.name, n.clone())));
-    let mut depth = 0;
+/// Why a gate list can't be compiled into a circuit.
+#[derive(Debug, PartialEq, Eq)]
+enum CircuitError<'a> {
+    /// Wires of a cycle. Each wire reads from the one after it, and the path starts and
+    /// ends on the same wire.
+    Cycle(Vec<&'a str>),
+    /// A wire that more than one gate drives
+    MultipleDrivers(&'a str),
+}
 
-    while !queue.is_empty() {
-        depth += 1;
-        if depth > 1000 {
-            // println!("Infinite loop");
-            return values;
+impl Display for CircuitError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitError::Cycle(wires) => {
+                write!(f, "the gates form a cycle {}", wires.join(" <- "))
+            }
+            CircuitError::MultipleDrivers(wire) => {
+                write!(f, "wire {wire} is driven by more than one gate")
+            }
         }
-        let node = queue.pop().expect("wtf");
+    }
+}
 
-        if values.contains_key(node.name) {
-            continue;
+/// The gate list compiled to indexed wires. Wires that no gate drives are inputs, and
+/// `order` lists the gate outputs so that every gate comes after both of its inputs.
+struct Circuit<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+    gates: Vec<Option<Gate>>,
+    order: Vec<usize>,
+}
+
+impl<'a> Circuit<'a> {
+    /// Fails if the gates feed back into themselves or two gates drive the same wire.
+    fn new(nodes: &[Node<'a>]) -> Result<Circuit<'a>, CircuitError<'a>> {
+        let mut circuit = Circuit {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            gates: Vec::new(),
+            order: Vec::new(),
+        };
+
+        for node in nodes {
+            let mut inputs = [0; 2];
+            for (i, input) in node.inputs().enumerate() {
+                inputs[i] = circuit.intern(input);
+            }
+            let id = circuit.intern(node.name);
+            if circuit.gates[id].is_some() {
+                return Err(CircuitError::MultipleDrivers(node.name));
+            }
+            circuit.gates[id] = Some(Gate {
+                op: node.op,
+                inputs,
+            });
         }
 
-        let lvalue = values.get(node.left);
-        let rvalue = values.get(node.right);
+        circuit.order = circuit.topological_order().map_err(CircuitError::Cycle)?;
+        Ok(circuit)
+    }
+
+    fn intern(&mut self, name: &'a str) -> usize {
+        *self.ids.entry(name).or_insert_with(|| {
+            self.names.push(name);
+            self.gates.push(None);
+            self.names.len() - 1
+        })
+    }
+
+    fn wire(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
 
-        if lvalue.is_none() || rvalue.is_none() {
-            queue.push(node);
-            if lvalue.is_none() {
-                let lnode = nodes
-                    .get(node.left)
-                    .expect(format!("Invalid node {}", node.left).as_str());
-                queue.push(*lnode);
+    /// Ids of the wires named `<prefix>00`, `<prefix>01`, ... ordered from the least
+    /// significant bit.
+    fn bus(&self, prefix: char) -> Vec<usize> {
+        let mut wires: Vec<_> = self
+            .names
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.starts_with(prefix) && n[1..].chars().all(|c| c.is_ascii_digit()))
+            .map(|(id, n)| (n[1..].parse::<usize>().unwrap_or(0), id))
+            .collect();
+        wires.sort();
+        wires.into_iter().map(|(_, id)| id).collect()
+    }
+
+    fn topological_order(&self) -> Result<Vec<usize>, Vec<&'a str>> {
+        // 0 = not visited, 1 = on the current dfs path, 2 = done
+        let mut state = vec![0u8; self.names.len()];
+        let mut order = Vec::new();
+
+        for root in 0..self.names.len() {
+            if state[root] != 0 {
+                continue;
             }
-            if rvalue.is_none() {
-                let rnode = nodes
-                    .get(node.right)
-                    .expect(format!("Invalid node {}", node.right).as_str());
-                queue.push(*rnode);
+
+            state[root] = 1;
+            let mut stack = vec![(root, 0)];
+
+            while let Some(top) = stack.last_mut() {
+                let (wire, next) = *top;
+                let input = self.gates[wire].and_then(|g| g.inputs().get(next).copied());
+
+                let Some(input) = input else {
+                    state[wire] = 2;
+                    if self.gates[wire].is_some() {
+                        order.push(wire);
+                    }
+                    stack.pop();
+                    continue;
+                };
+
+                top.1 += 1;
+                match state[input] {
+                    0 => {
+                        state[input] = 1;
+                        stack.push((input, 0));
+                    }
+                    1 => {
+                        let start = stack.iter().position(|&(w, _)| w == input).unwrap();
+                        return Err(stack[start..]
+                            .iter()
+                            .map(|&(w, _)| self.names[w])
+                            .chain([self.names[input]])
+                            .collect());
+                    }
+                    _ => {}
+                }
             }
-            continue;
         }
 
-        let value = match node.op {
-            Operation::And => lvalue.unwrap() & rvalue.unwrap(),
-            Operation::Or => lvalue.unwrap() | rvalue.unwrap(),
-            Operation::Xor => lvalue.unwrap() ^ rvalue.unwrap(),
-        };
-        values.insert(node.name, value);
+        Ok(order)
     }
-    values
-}
 
-fn part1(nodes: &Vec<Node>, values: &NodeValues) -> i64 {
-    let values = resolve_values(&nodes, &values);
-    let mut swapped_nodes = nodes.clone();
-    swap_nodes(&mut swapped_nodes, "z05", "tst");
-    println!("{:?}", swapped_nodes);
-    for i in 0..45 {
-        println!("OUT{i}");
-        println!(
-            "{}",
-            show_graph(format!("z{i:0>2}").as_str(), &swapped_nodes, &values)
-        );
+    /// Evaluates 64 input vectors at once, one per bit lane. `inputs` holds the lanes of
+    /// the input wires indexed by wire id, and the result holds the lanes of every wire.
+    fn evaluate_lanes(&self, inputs: &[u64]) -> Vec<u64> {
+        let mut lanes = inputs.to_vec();
+        lanes.resize(self.names.len(), 0);
+
+        for &wire in &self.order {
+            let gate = self.gates[wire].expect("Only gates are ordered");
+            let input = |i: usize| gate.inputs().get(i).map_or(0, |&w| lanes[w]);
+            lanes[wire] = gate.op.apply(input(0), input(1));
+        }
+
+        lanes
+    }
+
+    /// Inputs missing from `values` are treated as 0.
+    fn evaluate(&self, values: &NodeValues) -> NodeValues<'a> {
+        let mut inputs = vec![0; self.names.len()];
+        for (name, &value) in values {
+            if let Some(id) = self.wire(name) {
+                inputs[id] = if value { !0 } else { 0 };
+            }
+        }
+
+        let lanes = self.evaluate_lanes(&inputs);
+
+        HashMap::from_iter(
+            self.names
+                .iter()
+                .zip(lanes)
+                .map(|(&name, lane)| (name, lane & 1 == 1)),
+        )
+    }
+
+    /// Feeds each `(x, y)` pair to the x and y buses and reads back the z bus, running up
+    /// to 64 pairs per pass. Operand bits beyond the width of a bus are ignored.
+    fn evaluate_buses(&self, operands: &[(BigUint, BigUint)]) -> Vec<BigUint> {
+        let x_bus = self.bus('x');
+        let y_bus = self.bus('y');
+        let z_bus = self.bus('z');
+
+        let mut res = Vec::with_capacity(operands.len());
+
+        for chunk in operands.chunks(64) {
+            let mut inputs = vec![0u64; self.names.len()];
+            for (lane, (x, y)) in chunk.iter().enumerate() {
+                for (value, bus) in [(x, &x_bus), (y, &y_bus)] {
+                    for (bit, &wire) in bus.iter().enumerate() {
+                        if value.bit(bit as u64) {
+                            inputs[wire] |= 1 << lane;
+                        }
+                    }
+                }
+            }
+
+            let lanes = self.evaluate_lanes(&inputs);
+
+            res.extend((0..chunk.len()).map(|lane| {
+                let mut z = BigUint::default();
+                for (bit, &wire) in z_bus.iter().enumerate() {
+                    z.set_bit(bit as u64, (lanes[wire] >> lane) & 1 == 1);
+                }
+                z
+            }));
+        }
+
+        res
     }
 
+    /// Names of the gates feeding `wires`, including the gates driving them, in
+    /// evaluation order.
+    fn cone(&self, wires: &[usize]) -> Vec<&'a str> {
+        let mut in_cone = vec![false; self.names.len()];
+        let mut stack = wires.to_vec();
+
+        while let Some(wire) = stack.pop() {
+            if in_cone[wire] {
+                continue;
+            }
+            in_cone[wire] = true;
+            if let Some(gate) = self.gates[wire] {
+                stack.extend(gate.inputs());
+            }
+        }
+
+        self.order
+            .iter()
+            .filter(|&&w| in_cone[w])
+            .map(|&w| self.names[w])
+            .collect()
+    }
+}
+
+fn resolve_values<'a>(
+    nodes: &[Node<'a>],
+    values: &NodeValues<'a>,
+) -> Result<NodeValues<'a>, CircuitError<'a>> {
+    Ok(Circuit::new(nodes)?.evaluate(values))
+}
+
+fn part1<'a>(nodes: &[Node<'a>], values: &NodeValues<'a>) -> Result<BigUint, CircuitError<'a>> {
+    let values = resolve_values(nodes, values)?;
+
     let mut z_nodes: Vec<_> = nodes
         .iter()
         .map(|n| n.name)
         .filter(|n: &&str| n.starts_with("z"))
         .collect();
-    z_nodes.sort();
+    // Shorter names first so z100 sorts after z99
+    z_nodes.sort_by_key(|n| (n.len(), *n));
     z_nodes.reverse();
 
-    let mut res = 0i64;
+    let mut res = BigUint::default();
 
     for z_node in z_nodes {
         let value = values
@@ -151,147 +418,127 @@ fn part1(nodes: &Vec<Node>, values: &NodeValues) -> i64 {
         res <<= 1;
 
         if *value {
-            res |= 1;
+            res.set_bit(0, true);
         }
     }
 
-    res
+    Ok(res)
 }
 
-fn show_graph(node: &str, nodes: &Vec<Node>, values: &NodeValues) -> String {
-    let mut res = String::new();
-    let mut stack = vec![(0, Some(node))];
+/// Inputs of a gate list: wires that are read but never driven, sorted by name.
+fn input_wires<'a>(nodes: &[Node<'a>]) -> Vec<&'a str> {
+    let driven: HashSet<&str> = HashSet::from_iter(nodes.iter().map(|n| n.name));
+    let mut inputs: Vec<_> = nodes
+        .iter()
+        .flat_map(|n| n.inputs())
+        .filter(|n| !driven.contains(n))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    inputs.sort();
+    inputs
+}
 
-    while !stack.is_empty() {
-        let (depth, top) = stack.pop().unwrap();
-        let margin = String::from("     |   ").repeat(depth);
+/// Outputs of a gate list: every z wire plus any driven wire that no gate reads, sorted
+/// by name.
+fn output_wires<'a>(nodes: &[Node<'a>]) -> Vec<&'a str> {
+    let read: HashSet<&str> = HashSet::from_iter(nodes.iter().flat_map(|n| n.inputs()));
+    let mut outputs: Vec<_> = nodes
+        .iter()
+        .map(|n| n.name)
+        .filter(|n| n.starts_with('z') || !read.contains(n))
+        .collect();
+    outputs.sort();
+    outputs
+}
 
-        if top.is_none() {
-            res += "\n";
-            res += margin.as_str();
-            continue;
-        }
+/// Graphviz graph of the gate list with one box per gate coloured by its operation.
+/// Wires in `suspects` are drawn in red so swapped outputs stand out.
+fn to_dot(nodes: &[Node], suspects: &HashSet<&str>) -> String {
+    let mut res = String::from("digraph circuit {\n    rankdir=LR;\n");
 
-        let top = top.unwrap();
+    for input in input_wires(nodes) {
+        res += &format!("    \"{input}\" [shape=circle];\n");
+    }
 
-        let nop = nodes
-            .iter()
-            .find(|n| n.name == top)
-            .map(|n| n.op)
-            .unwrap_or(Operation::And);
-
-        res += " - ";
-        res += format!("{}{top}", nop).as_str();
-        if let Some(val) = values.get(top) {
-            if *val {
-                res += " 1";
-            } else {
-                res += " 0";
-            }
+    for node in nodes {
+        let colour = match node.op {
+            Operation::And => "lightblue",
+            Operation::Nand => "lightskyblue",
+            Operation::Or => "palegreen",
+            Operation::Nor => "darkseagreen",
+            Operation::Xor => "khaki",
+            Operation::Xnor => "gold",
+            Operation::Not => "plum",
+            Operation::Const(_) => "lightgrey",
+        };
+        let border = if suspects.contains(node.name) {
+            ", color=red, penwidth=3"
         } else {
-            res += "  ";
-        }
-
-        if let Some(n) = nodes.iter().find(|n| n.name == top) {
-            if depth < 500 {
-                stack.push((depth + 1, Some(n.right)));
-                stack.push((depth + 1, None));
-                stack.push((depth + 1, Some(n.left)));
-            }
-        }
+            ""
+        };
+        res += &format!(
+            "    \"{}\" [shape=box, style=filled, fillcolor={colour}, label=\"{}\\n{}\"{border}];\n",
+            node.name,
+            node.name,
+            node.op.name()
+        );
     }
 
-    return res;
-}
-
-fn get_nodes<'a>(node: &'a str, nodes: &'a [Node]) -> Vec<&'a str> {
-    let mut res = Vec::new();
-    let mut queue = vec![node];
-
-    while !queue.is_empty() {
-        let n = queue.pop().unwrap();
-        res.push(n);
-
-        if let Some(node) = nodes.iter().find(|x| x.name == n) {
-            queue.push(node.left);
-            queue.push(node.right);
+    for node in nodes {
+        for input in node.inputs() {
+            res += &format!("    \"{input}\" -> \"{}\";\n", node.name);
         }
     }
 
+    res += "}\n";
     res
 }
 
-fn test_bit<'a>(
-    bit: i32,
-    nodes: &'a Vec<Node>,
-    init_values: &'a NodeValues,
-) -> (bool, bool, bool, Vec<String>) {
-    let x_node = format!("x{bit:0>2}");
-    let y_node = format!("y{bit:0>2}");
-    let z_node = format!("z{bit:0>2}");
-    let z_carry_node = format!("z{:0>2}", bit + 1);
-
-    let mut test_res = [true, true, true];
-    let mut invalid_positive_nodes = Vec::new();
-
-    // test set x
-    let mut values = init_values.clone();
-    values.insert(x_node.as_str(), true);
-
-    let output = resolve_values(&nodes, &values);
-    if !output.get(z_node.as_str()).unwrap_or(&false) {
-        // potentially_bad_nodes.extend(pos_nodes.iter().map(|(n, _)| n.to_string()));
-        invalid_positive_nodes.extend(
-            output
-                .iter()
-                .filter(|(&n, &v)| n != x_node && v)
-                .map(|(&n, _)| n),
-        );
-        test_res[0] = false;
-    }
+/// Structural Verilog module with one continuous assignment per gate.
+fn to_verilog(nodes: &[Node], module: &str) -> String {
+    let inputs = input_wires(nodes);
+    let outputs = output_wires(nodes);
+    let output_set: HashSet<&str> = HashSet::from_iter(outputs.iter().copied());
 
-    // test set y
-    let mut values = init_values.clone();
-    values.insert(y_node.as_str(), true);
+    let ports: Vec<_> = inputs.iter().chain(outputs.iter()).copied().collect();
+    let mut res = format!("module {module}({});\n", ports.join(", "));
 
-    let output = resolve_values(&nodes, &values);
-    if !output.get(z_node.as_str()).unwrap_or(&false) {
-        // potentially_bad_nodes.extend(pos_nodes.iter().map(|(n, _)| n.to_string()));
-        invalid_positive_nodes.extend(
-            output
-                .iter()
-                .filter(|(&n, &v)| n != y_node && v)
-                .map(|(&n, _)| n),
-        );
-        test_res[1] = false;
+    for input in &inputs {
+        res += &format!("    input {input};\n");
+    }
+    for output in &outputs {
+        res += &format!("    output {output};\n");
     }
 
-    // test set both
-    let mut values = init_values.clone();
-    values.insert(x_node.as_str(), true);
-    values.insert(y_node.as_str(), true);
+    let mut internal: Vec<_> = nodes
+        .iter()
+        .map(|n| n.name)
+        .filter(|n| !output_set.contains(n))
+        .collect();
+    internal.sort();
+    for wire in internal {
+        res += &format!("    wire {wire};\n");
+    }
 
-    let output = resolve_values(&nodes, &values);
-    if !output.get(z_carry_node.as_str()).unwrap_or(&false) {
-        // potentially_bad_nodes.extend(pos_nodes.iter().map(|(n, _)| n.to_string()));
-        invalid_positive_nodes.extend(
-            output
-                .iter()
-                .filter(|(&n, &v)| n != x_node && v)
-                .map(|(&n, _)| n),
-        );
-        test_res[1] = false;
+    res += "\n";
+    for node in nodes {
+        let (left, right) = (node.left, node.right);
+        let expr = match node.op {
+            Operation::And | Operation::Or | Operation::Xor => {
+                format!("{left} {} {right}", node.op)
+            }
+            Operation::Nand => format!("~({left} & {right})"),
+            Operation::Nor => format!("~({left} | {right})"),
+            Operation::Xnor => format!("~({left} ^ {right})"),
+            Operation::Not => format!("~{left}"),
+            Operation::Const(value) => format!("1'b{}", value as u8),
+        };
+        res += &format!("    assign {} = {expr};\n", node.name);
     }
 
-    (
-        test_res[0],
-        test_res[1],
-        test_res[2],
-        invalid_positive_nodes
-            .iter()
-            .map(|s| s.to_string())
-            .collect(),
-    )
+    res += "endmodule\n";
+    res
 }
 
 fn swap_nodes<'a>(nodes: &mut Vec<Node<'a>>, node0: &'a str, node1: &'a str) {
@@ -304,196 +551,415 @@ fn swap_nodes<'a>(nodes: &mut Vec<Node<'a>>, node0: &'a str, node1: &'a str) {
     }
 }
 
-fn part2(nodes: &Vec<Node>) -> String {
-    let mut z_nodes: Vec<_> = nodes
-        .iter()
-        .map(|n| n.name)
-        .filter(|n| n.starts_with("z"))
-        .collect();
-    z_nodes.sort();
+/// A pair of gate outputs that have to be swapped back, and why.
+#[derive(Debug)]
+struct Repair<'a> {
+    bit: usize,
+    swap: (&'a str, &'a str),
+    reason: String,
+}
 
-    let x_nodes: Vec<_> = (0..z_nodes.len() - 1)
-        .map(|i| format!("x{i:0>2}"))
-        .collect();
-    let y_nodes: Vec<_> = (0..z_nodes.len() - 1)
-        .map(|i| format!("y{i:0>2}"))
-        .collect();
+impl Display for Repair<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bit {}: swap {} and {}, {}",
+            self.bit, self.swap.0, self.swap.1, self.reason
+        )
+    }
+}
 
-    let init_values: HashMap<&str, bool> = HashMap::from_iter(
-        x_nodes
-            .iter()
-            .map(|n| (n.as_str(), false))
-            .chain(y_nodes.iter().map(|n| (n.as_str(), false))),
-    );
+/// Why `repair_adder` gave up on a circuit: the gate it expected at `bit` is missing, and
+/// no single swap explains it.
+#[derive(Debug, PartialEq, Eq)]
+struct RepairError {
+    bit: usize,
+    expected: String,
+}
 
-    assert_eq!(x_nodes.len(), y_nodes.len());
-    assert_eq!(z_nodes.len(), x_nodes.len() + 1);
-
-    let mut swaps = HashSet::new();
-    for i in 0..x_nodes.len() {
-        let x_node = &x_nodes[i];
-        let y_node = &y_nodes[i];
-        let z_node = z_nodes[i];
-        let z_carry_node = z_nodes[i + 1];
-
-        let mut potentially_bad_nodes: Vec<String> = Vec::new();
-
-        // test set x
-        let mut values = init_values.clone();
-        values.insert(x_node.as_str(), true);
-
-        let output = resolve_values(&nodes, &values);
-        let pos_nodes: Vec<_> = output.iter().filter(|(&n, &v)| n != x_node && v).collect();
-        if !output.get(z_node).unwrap_or(&false) {
-            println!("Bad at {x_node}: {pos_nodes:?}");
-            println!("{}", show_graph(z_node, nodes, &output));
-            for (n, _) in &pos_nodes {
-                println!("{n}:");
-                println!("{}", show_graph(n, nodes, &output));
-            }
-            potentially_bad_nodes.extend(pos_nodes.iter().map(|(n, _)| n.to_string()));
-        }
+impl Display for RepairError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bit {}: expected {}", self.bit, self.expected)
+    }
+}
 
-        // test set y
-        let mut values = init_values.clone();
-        values.insert(y_node.as_str(), true);
+/// What `check_adder_bit` makes of one bit
+enum BitCheck<'a> {
+    /// The bit is a proper adder, with its carry out
+    Matches(&'a str),
+    Broken(Repair<'a>),
+}
 
-        let output = resolve_values(&nodes, &values);
-        let pos_nodes: Vec<_> = output.iter().filter(|(&n, &v)| n != y_node && v).collect();
-        if !output.get(z_node).unwrap_or(&false) {
-            println!("Bad at {y_node}: {pos_nodes:?}");
-            potentially_bad_nodes.extend(pos_nodes.iter().map(|(n, _)| n.to_string()));
-        }
+type GateIndex<'a> = HashMap<(Operation, &'a str, &'a str), &'a str>;
 
-        // test set both
-        let mut values = init_values.clone();
-        values.insert(y_node.as_str(), true);
-        values.insert(x_node.as_str(), true);
+fn gate_index<'a>(nodes: &[Node<'a>]) -> GateIndex<'a> {
+    HashMap::from_iter(
+        nodes
+            .iter()
+            .map(|n| ((n.op, n.left.min(n.right), n.left.max(n.right)), n.name)),
+    )
+}
+
+fn find_gate<'a>(gates: &GateIndex<'a>, op: Operation, a: &'a str, b: &'a str) -> Option<&'a str> {
+    gates.get(&(op, a.min(b), a.max(b))).copied()
+}
 
-        let output = resolve_values(&nodes, &values);
-        let pos_nodes: Vec<_> = output
+/// The gate with operation `op` that reads `wire`, and its other input.
+fn find_reader<'a>(nodes: &[Node<'a>], op: Operation, wire: &str) -> Option<(&'a str, &'a str)> {
+    nodes
+        .iter()
+        .filter(|n| n.op == op)
+        .find_map(|n| match (n.left == wire, n.right == wire) {
+            (true, _) => Some((n.name, n.right)),
+            (_, true) => Some((n.name, n.left)),
+            _ => None,
+        })
+}
+
+/// Matches bit `bit` against a full adder (a half adder when there is no carry in):
+///
+/// sum = x XOR y, and = x AND y, z = sum XOR carry,
+/// carry_and = sum AND carry, carry_out = and OR carry_and
+///
+/// Returns the carry out or the first swap needed to make the bit match, and fails if
+/// the bit is too far from the pattern to tell which gates were swapped.
+fn check_adder_bit<'a>(
+    nodes: &[Node<'a>],
+    bit: usize,
+    carry: Option<&'a str>,
+) -> Result<BitCheck<'a>, RepairError> {
+    let gates = gate_index(nodes);
+    let missing = |expected: String| RepairError { bit, expected };
+    let wire = |name: String| -> Result<&'a str, RepairError> {
+        nodes
             .iter()
-            .filter(|(&n, &v)| n != x_node && n != y_node && v)
-            .collect();
-        if !output.get(z_carry_node).unwrap_or(&false) {
-            println!("Bad at {x_node} {y_node}: {pos_nodes:?}");
-            potentially_bad_nodes.extend(pos_nodes.iter().map(|(n, _)| n.to_string()));
+            .flat_map(|n| [n.name, n.left, n.right])
+            .find(|n| *n == name)
+            .ok_or_else(|| missing(format!("a wire {name}")))
+    };
+    let repair = |a: &'a str, b: &'a str, reason: String| {
+        Ok(BitCheck::Broken(Repair {
+            bit,
+            swap: (a, b),
+            reason,
+        }))
+    };
+
+    let x = wire(format!("x{bit:0>2}"))?;
+    let y = wire(format!("y{bit:0>2}"))?;
+    let z = wire(format!("z{bit:0>2}"))?;
+
+    let sum = find_gate(&gates, Operation::Xor, x, y)
+        .ok_or_else(|| missing(format!("a gate {x} XOR {y}")))?;
+    let and = find_gate(&gates, Operation::And, x, y)
+        .ok_or_else(|| missing(format!("a gate {x} AND {y}")))?;
+
+    let Some(carry) = carry else {
+        if sum != z {
+            return repair(sum, z, format!("{x} XOR {y} drives {sum} instead of {z}"));
+        }
+        return Ok(BitCheck::Matches(and));
+    };
+
+    let out = match find_gate(&gates, Operation::Xor, sum, carry) {
+        Some(out) => out,
+        None => {
+            let expected = || missing(format!("a gate {sum} XOR {carry} -> {z}"));
+            let driver = nodes
+                .iter()
+                .find(|n| n.name == z && n.op == Operation::Xor)
+                .ok_or_else(expected)?;
+
+            return if driver.left == carry || driver.right == carry {
+                let other = if driver.left == carry {
+                    driver.right
+                } else {
+                    driver.left
+                };
+                repair(
+                    sum,
+                    other,
+                    format!("{z} reads {other} where {x} XOR {y} drives {sum}"),
+                )
+            } else if driver.left == sum || driver.right == sum {
+                let other = if driver.left == sum {
+                    driver.right
+                } else {
+                    driver.left
+                };
+                repair(
+                    carry,
+                    other,
+                    format!(
+                        "{z} reads {other} where the carry of bit {} is {carry}",
+                        bit - 1
+                    ),
+                )
+            } else {
+                Err(expected())
+            };
         }
+    };
 
-        // Try swapping nodes around to find the correct answer
-        let bad_node_set: HashSet<&str> =
-            HashSet::from_iter(potentially_bad_nodes.iter().map(|n| n.as_str()));
-        let nodes_in_output = get_nodes(z_node, &nodes);
-        let nodes_in_carry = get_nodes(z_carry_node, &nodes);
-        let nodes_in_output: Vec<_> = nodes_in_output
-            .iter()
-            .chain(nodes_in_carry.iter())
-            .collect();
+    if out != z {
+        return repair(
+            out,
+            z,
+            format!("{sum} XOR {carry} drives {out} instead of {z}"),
+        );
+    }
 
-        for bad_on_node in bad_node_set {
-            for bad_off_node in &nodes_in_output {
-                if bad_on_node.starts_with("z") && bad_off_node.starts_with("z") {
-                    continue;
+    let carry_and = find_gate(&gates, Operation::And, sum, carry)
+        .ok_or_else(|| missing(format!("a gate {sum} AND {carry}")))?;
+
+    match find_gate(&gates, Operation::Or, and, carry_and) {
+        Some(carry_out) => Ok(BitCheck::Matches(carry_out)),
+        None => {
+            for (known, expected) in [(and, carry_and), (carry_and, and)] {
+                if let Some((carry_out, other)) = find_reader(nodes, Operation::Or, known) {
+                    return repair(
+                        expected,
+                        other,
+                        format!("{carry_out} reads {known} and {other} instead of {expected}"),
+                    );
                 }
+            }
+            Err(missing(format!("a gate {and} OR {carry_and}")))
+        }
+    }
+}
 
-                if bad_off_node.starts_with("x") || bad_off_node.starts_with("y") {
-                    continue;
-                }
+/// Walks a ripple-carry adder from the least significant bit and swaps back every pair of
+/// gate outputs that breaks the full adder pattern.
+fn repair_adder<'a>(nodes: &[Node<'a>]) -> Result<Vec<Repair<'a>>, RepairError> {
+    let mut nodes = nodes.to_vec();
+    let bits = input_wires(&nodes)
+        .iter()
+        .filter(|n| n.starts_with('x'))
+        .count();
+    if bits == 0 {
+        return Err(RepairError {
+            bit: 0,
+            expected: "an input x00".to_string(),
+        });
+    }
 
-                if bad_on_node == **bad_off_node {
-                    continue;
+    let mut repairs: Vec<Repair> = Vec::new();
+    let mut carry = None;
+    let mut bit = 0;
+
+    while bit <= bits {
+        let checked = match carry {
+            Some(carry) if bit == bits => {
+                // The carry out of the top bit is the last output
+                let z = format!("z{bits:0>2}");
+                match nodes.iter().find(|n| n.name == z) {
+                    Some(n) if n.name != carry => BitCheck::Broken(Repair {
+                        bit,
+                        swap: (carry, n.name),
+                        reason: format!("the final carry drives {carry} instead of {z}"),
+                    }),
+                    _ => BitCheck::Matches(carry),
                 }
+            }
+            _ => check_adder_bit(&nodes, bit, carry)?,
+        };
 
-                let mut swapped_nodes = nodes.clone();
-
-                swap_nodes(&mut swapped_nodes, bad_on_node, bad_off_node);
-
-                let (t1, t2, t3, _) = test_bit(i as i32, &swapped_nodes, &init_values);
-                if t1 && t2 && t3 {
-                    println!("Successfully swapped {bad_on_node} and {}", *bad_off_node);
-                    swaps.insert((bad_on_node.to_string(), bad_off_node.to_string()));
+        match checked {
+            BitCheck::Matches(carry_out) => {
+                carry = Some(carry_out);
+                bit += 1;
+            }
+            BitCheck::Broken(repair) => {
+                // Swaps that keep undoing each other never get past this bit
+                if repairs.len() >= 2 * bits {
+                    return Err(RepairError {
+                        bit,
+                        expected: format!("a fix within {} swaps", 2 * bits),
+                    });
                 }
+                let (a, b) = repair.swap;
+                swap_nodes(&mut nodes, a, b);
+                carry = carry.map(|c| {
+                    if c == a {
+                        b
+                    } else if c == b {
+                        a
+                    } else {
+                        c
+                    }
+                });
+                repairs.push(repair);
             }
         }
     }
 
-    println!("Swaps: {swaps:?}");
-
-    let mut si0 = 0;
-    let mut si1 = 1;
-    let mut si2 = 2;
-    let mut si3 = 3;
-
-    let mut best: Option<(Vec<i32>, [&(String, String); 4])> = None;
-
-    let swaps: Vec<_> = swaps.iter().collect();
-    loop {
-        println!("Testing swap {si0}, {si1}, {si2}, {si3}");
-        let swap_0 = &swaps[si0];
-        let swap_1 = &swaps[si1];
-        let swap_2 = &swaps[si2];
-        let swap_3 = &swaps[si3];
-
-        let mut test_swaps = nodes.clone();
-        swap_nodes(&mut test_swaps, swap_0.0.as_str(), swap_0.1.as_str());
-        swap_nodes(&mut test_swaps, swap_1.0.as_str(), swap_1.1.as_str());
-        swap_nodes(&mut test_swaps, swap_2.0.as_str(), swap_2.1.as_str());
-        swap_nodes(&mut test_swaps, swap_3.0.as_str(), swap_3.1.as_str());
-
-        let failed_bits: Vec<_> = (0..44)
-            .map(|bit| {
-                let (r1, r2, r3, _) = test_bit(bit, &test_swaps, &init_values);
-                (bit, r1 && r2 && r3)
-            })
-            .filter(|(_, b)| !b)
-            .map(|(b, _)| b)
-            .collect();
+    Ok(repairs)
+}
 
-        if let Some((ref b, _)) = best {
-            if failed_bits.len() < b.len() {
-                best = Some((failed_bits.clone(), [swap_0, swap_1, swap_2, swap_3]));
-            }
-        } else {
-            best = Some((failed_bits.clone().into(), [swap_0, swap_1, swap_2, swap_3]));
-        }
+/// First operand pair where a circuit disagrees with integer addition.
+#[derive(Debug)]
+struct Mismatch<'a> {
+    x: BigUint,
+    y: BigUint,
+    expected: BigUint,
+    actual: BigUint,
+    bits: Vec<usize>,
+    cone: Vec<&'a str>,
+}
+
+impl Display for Mismatch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} + {} should be {} but the circuit gives {}",
+            self.x, self.y, self.expected, self.actual
+        )?;
+        let bits: Vec<_> = self.bits.iter().map(|b| format!("z{b:0>2}")).collect();
+        writeln!(f, "Differing bits: {}", bits.join(", "))?;
+        write!(f, "Gates feeding them: {}", self.cone.join(", "))
+    }
+}
+
+/// Operand pairs that exercise the carry chain: zero, all ones, alternating bits, single
+/// bits, and carries rippling in from each position.
+fn corner_cases(width: usize) -> Vec<(BigUint, BigUint)> {
+    let zero = BigUint::default();
+    let one = BigUint::from(1u8);
+    let mask = (&one << width) - 1u8;
+
+    let mut alternating = BigUint::default();
+    for bit in (0..width).step_by(2) {
+        alternating.set_bit(bit as u64, true);
+    }
+    let inverted = &alternating ^ &mask;
+
+    let mut res = vec![
+        (zero.clone(), zero.clone()),
+        (mask.clone(), zero.clone()),
+        (zero.clone(), mask.clone()),
+        (mask.clone(), mask.clone()),
+        (mask.clone(), one.clone()),
+        (alternating.clone(), alternating.clone()),
+        (alternating, inverted.clone()),
+        (inverted.clone(), inverted),
+    ];
+
+    for bit in 0..width {
+        let single = &one << bit;
+        res.push((single.clone(), zero.clone()));
+        res.push((zero.clone(), single.clone()));
+        res.push((single.clone(), single.clone()));
+        res.push((&mask >> bit << bit, single));
+    }
+
+    res
+}
 
-        if failed_bits.is_empty() {
-            let mut sw = vec![
-                swap_0.clone().0.clone(),
-                swap_0.clone().1.clone(),
-                swap_1.clone().0.clone(),
-                swap_1.clone().1.clone(),
-                swap_2.clone().0.clone(),
-                swap_2.clone().1.clone(),
-                swap_3.clone().0.clone(),
-                swap_3.clone().1.clone(),
-            ];
-            sw.sort();
-            return sw.join(",");
+fn random_operand(rng: &mut Rng, width: usize) -> BigUint {
+    let words: Vec<u32> = (0..width.div_ceil(32))
+        .map(|_| rng.next_u64() as u32)
+        .collect();
+    let mask = (BigUint::from(1u8) << width) - 1u8;
+    BigUint::new(words) & mask
+}
+
+/// Most operand bits `verify_adder` tries exhaustively, whatever it is asked for. Beyond
+/// this there are too many pairs to try, and the shifts in the operand range overflow.
+const MAX_EXHAUSTIVE_BITS: usize = 32;
+
+/// Checks that the circuit adds its x and y buses into z: exhaustively when both operands
+/// together have at most `exhaustive_bits` bits, capped at `MAX_EXHAUSTIVE_BITS`,
+/// otherwise over the corner cases plus `samples` random pairs.
+fn verify_adder<'a>(
+    circuit: &Circuit<'a>,
+    exhaustive_bits: usize,
+    samples: usize,
+    seed: u64,
+) -> Result<(), Box<Mismatch<'a>>> {
+    let width = circuit.bus('x').len();
+
+    let exhaustive = 2 * width <= exhaustive_bits.min(MAX_EXHAUSTIVE_BITS);
+    let operands: Vec<(BigUint, BigUint)> = if exhaustive {
+        let max = (1u64 << width) - 1;
+        (0..=max)
+            .flat_map(|x| (0..=max).map(move |y| (BigUint::from(x), BigUint::from(y))))
+            .collect()
+    } else {
+        let mut rng = Rng::new(seed);
+        let mut operands = corner_cases(width);
+        for _ in 0..samples {
+            let x = random_operand(&mut rng, width);
+            let y = random_operand(&mut rng, width);
+            operands.push((x, y));
         }
+        operands
+    };
 
-        if si3 < swaps.len() - 1 {
-            si3 += 1;
-        } else if si2 < si3 - 1 {
-            si2 += 1;
-            si3 = si2 + 1;
-        } else if si1 < si2 - 1 {
-            si1 += 1;
-            si2 = si1 + 1;
-            si3 = si2 + 1;
-        } else if si0 < si1 - 1 {
-            si0 += 1;
-            si1 = si0 + 1;
-            si2 = si1 + 1;
-            si3 = si2 + 1;
-        } else {
-            break;
+    let z_bus = circuit.bus('z');
+
+    for ((x, y), actual) in operands.iter().zip(circuit.evaluate_buses(&operands)) {
+        let expected = x + y;
+        if expected == actual {
+            continue;
         }
+
+        let diff = &expected ^ &actual;
+        let bits: Vec<_> = (0..z_bus.len()).filter(|&b| diff.bit(b as u64)).collect();
+        let wires: Vec<_> = bits.iter().map(|&b| z_bus[b]).collect();
+
+        return Err(Box::new(Mismatch {
+            x: x.clone(),
+            y: y.clone(),
+            expected,
+            actual,
+            bits,
+            cone: circuit.cone(&wires),
+        }));
+    }
+
+    Ok(())
+}
+
+fn part2(nodes: &[Node]) -> String {
+    match repair_adder(nodes) {
+        Ok(repairs) => swapped_wires(&repairs).join(","),
+        Err(error) => format!("can not repair the adder, {error}"),
+    }
+}
+
+/// Prints each repair `repair_adder` finds and whether the repaired circuit adds.
+fn print_repairs(nodes: &[Node]) {
+    let Ok(repairs) = repair_adder(nodes) else {
+        return;
+    };
+    let mut repaired = nodes.to_vec();
+    for repair in &repairs {
+        println!("{repair}");
+        swap_nodes(&mut repaired, repair.swap.0, repair.swap.1);
     }
 
-    println!("Best: {best:?}");
+    match Circuit::new(&repaired).map(|c| verify_adder(&c, 16, 10000, 24)) {
+        Ok(Ok(())) => println!("Repaired adder verified"),
+        Ok(Err(mismatch)) => println!("Repaired adder is still wrong: {mismatch}"),
+        Err(error) => println!("Repaired adder is broken: {error}"),
+    }
+}
+
+/// Every wire that the repairs swap, sorted by name.
+fn swapped_wires<'a>(repairs: &[Repair<'a>]) -> Vec<&'a str> {
+    let mut wires: Vec<_> = repairs.iter().flat_map(|r| [r.swap.0, r.swap.1]).collect();
+    wires.sort();
+    wires
+}
 
-    "hej".into()
+/// DOT graph of the gate list as given, with the outputs `repair_adder` swaps back marked.
+fn to_repair_dot(nodes: &[Node]) -> String {
+    let suspects = match repair_adder(nodes) {
+        Ok(repairs) => HashSet::from_iter(swapped_wires(&repairs)),
+        Err(_) => HashSet::new(),
+    };
+    to_dot(nodes, &suspects)
 }
 
 pub fn day24() {
@@ -501,8 +967,28 @@ pub fn day24() {
 
     let (nodes, values) = parse_input(&input);
 
-    println!("Part 1: {}", part1(&nodes, &values));
+    match part1(&nodes, &values) {
+        Ok(z) => println!("Part 1: {z}"),
+        Err(error) => println!("Part 1: can not evaluate, {error}"),
+    }
+    print_repairs(&nodes);
     println!("Part 2: {}", part2(&nodes));
+
+    // Exports go under outputs/, next to the inputs/ directory the input is read from
+    if let Err(error) = fs::create_dir_all("outputs") {
+        println!("Could not create outputs: {error}");
+        return;
+    }
+    let exports = [
+        ("outputs/day24.dot", to_repair_dot(&nodes)),
+        ("outputs/day24.v", to_verilog(&nodes, "day24")),
+    ];
+    for (path, contents) in exports {
+        match fs::write(path, contents) {
+            Ok(()) => println!("Wrote {path}"),
+            Err(error) => println!("Could not write {path}: {error}"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -561,6 +1047,284 @@ tnw OR pbm -> gnj";
 
         let (nodes, values) = parse_input(&input);
 
-        assert_eq!(2024, part1(&nodes, &values));
+        assert_eq!(BigUint::from(2024u32), part1(&nodes, &values).unwrap());
+    }
+
+    /// Gate list of a ripple-carry adder over `bits`-bit x and y, with all inputs at 0.
+    fn adder(bits: usize) -> String {
+        let mut res = String::new();
+        for i in 0..bits {
+            res += &format!("x{i:0>2}: 0\ny{i:0>2}: 0\n");
+        }
+        res += "\n";
+        res += "x00 XOR y00 -> z00\nx00 AND y00 -> c00\n";
+        for i in 1..bits {
+            let carry_in = format!("c{:0>2}", i - 1);
+            let carry_out = if i == bits - 1 {
+                format!("z{bits:0>2}")
+            } else {
+                format!("c{i:0>2}")
+            };
+            res += &format!("x{i:0>2} XOR y{i:0>2} -> s{i:0>2}\n");
+            res += &format!("x{i:0>2} AND y{i:0>2} -> a{i:0>2}\n");
+            res += &format!("s{i:0>2} XOR {carry_in} -> z{i:0>2}\n");
+            res += &format!("s{i:0>2} AND {carry_in} -> b{i:0>2}\n");
+            res += &format!("a{i:0>2} OR b{i:0>2} -> {carry_out}\n");
+        }
+        res
+    }
+
+    #[test]
+    fn test_circuit_lanes() {
+        let input = adder(8);
+        let (nodes, _) = parse_input(&input);
+        let circuit = Circuit::new(&nodes).expect("Adder has no cycles");
+
+        let operands: Vec<_> = (0..200u64)
+            .map(|i| (BigUint::from(i * 37 % 256), BigUint::from(i * 101 % 256)))
+            .collect();
+        let sums = circuit.evaluate_buses(&operands);
+
+        for ((x, y), z) in operands.iter().zip(sums) {
+            assert_eq!(x + y, z);
+        }
+    }
+
+    #[test]
+    fn test_circuit_cycle() {
+        let input = "x00: 1
+y00: 1
+
+x00 AND abc -> def
+def XOR y00 -> ghi
+ghi OR x00 -> abc
+def AND y00 -> z00";
+
+        let (nodes, _) = parse_input(input);
+        let Some(CircuitError::Cycle(cycle)) = Circuit::new(&nodes).err() else {
+            panic!("Should find the cycle");
+        };
+
+        assert_eq!(4, cycle.len());
+        assert_eq!(cycle[0], cycle[3]);
+        let mut wires = cycle[..3].to_vec();
+        wires.sort();
+        assert_eq!(vec!["abc", "def", "ghi"], wires);
+
+        // The cycle reaches the caller instead of being swallowed
+        let Err(CircuitError::Cycle(cycle)) = part1(&nodes, &HashMap::new()) else {
+            panic!("Should report the cycle");
+        };
+        assert_eq!(4, cycle.len());
+    }
+
+    #[test]
+    fn test_multiple_drivers() {
+        let input = "x00: 1
+y00: 0
+
+x00 AND y00 -> z00
+x00 OR y00 -> z00";
+
+        let (nodes, values) = parse_input(input);
+        assert_eq!(
+            Some(CircuitError::MultipleDrivers("z00")),
+            Circuit::new(&nodes).err()
+        );
+        assert_eq!(
+            Err(CircuitError::MultipleDrivers("z00")),
+            resolve_values(&nodes, &values)
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let input = adder(2);
+        let (nodes, _) = parse_input(&input);
+        let dot = to_dot(&nodes, &HashSet::from(["z01"]));
+
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains("\"x00\" [shape=circle];"));
+        assert!(dot.contains(
+            "\"c00\" [shape=box, style=filled, fillcolor=lightblue, label=\"c00\\nAND\"];"
+        ));
+        assert!(dot.contains("label=\"z01\\nXOR\", color=red, penwidth=3];"));
+        assert!(dot.contains("\"s01\" -> \"z01\";"));
+    }
+
+    #[test]
+    fn test_verilog() {
+        let input = adder(2);
+        let (nodes, _) = parse_input(&input);
+        let verilog = to_verilog(&nodes, "adder");
+
+        assert!(verilog.starts_with("module adder(x00, x01, y00, y01, z00, z01, z02);"));
+        assert!(verilog.contains("    input x00;\n"));
+        assert!(verilog.contains("    output z02;\n"));
+        assert!(verilog.contains("    wire c00;\n"));
+        assert!(verilog.contains("    assign z02 = a01 | b01;\n"));
+        assert!(verilog.ends_with("endmodule\n"));
+    }
+
+    #[test]
+    fn test_repair_adder() {
+        let input = adder(12);
+        let (mut nodes, _) = parse_input(&input);
+        assert!(repair_adder(&nodes).unwrap().is_empty());
+
+        swap_nodes(&mut nodes, "z03", "c03");
+        swap_nodes(&mut nodes, "s05", "a05");
+        swap_nodes(&mut nodes, "b08", "z08");
+        swap_nodes(&mut nodes, "c09", "a10");
+
+        let repairs = repair_adder(&nodes).unwrap();
+        assert_eq!(4, repairs.len());
+        assert_eq!("a05,a10,b08,c03,c09,s05,z03,z08", part2(&nodes));
+
+        // The wires that get swapped back are the ones marked in the DOT graph
+        let dot = to_repair_dot(&nodes);
+        assert_eq!(8, dot.matches("color=red").count());
+        assert!(dot.contains("label=\"z03\\nOR\", color=red, penwidth=3];"));
+        assert!(dot.contains("label=\"c03\\nXOR\", color=red, penwidth=3];"));
+
+        for repair in &repairs {
+            swap_nodes(&mut nodes, repair.swap.0, repair.swap.1);
+        }
+        let circuit = Circuit::new(&nodes).expect("Repaired adder has no cycles");
+        assert!(verify_adder(&circuit, 0, 100, 1).is_ok());
+    }
+
+    #[test]
+    fn test_unrepairable_adder() {
+        // Without the carry AND gate of bit 2 there is nothing to swap
+        let input = adder(4).replace("s02 AND c01 -> b02\n", "");
+        let (nodes, _) = parse_input(&input);
+        let error = repair_adder(&nodes).expect_err("Adder is missing a gate");
+        assert_eq!(
+            RepairError {
+                bit: 2,
+                expected: "a gate s02 AND c01".to_string()
+            },
+            error
+        );
+        assert_eq!(
+            "can not repair the adder, bit 2: expected a gate s02 AND c01",
+            part2(&nodes)
+        );
+
+        let input = adder(4).replace("x01 XOR y01", "x01 OR y01");
+        let (nodes, _) = parse_input(&input);
+        assert_eq!(1, repair_adder(&nodes).unwrap_err().bit);
+    }
+
+    #[test]
+    fn test_random_swaps() {
+        let input = adder(45);
+        let (nodes, _) = parse_input(&input);
+        let gates: Vec<_> = nodes.iter().map(|n| n.name).collect();
+        let mut rng = Rng::new(28);
+        let mut repaired = 0;
+
+        for _ in 0..80 {
+            let mut swapped = nodes.clone();
+            for _ in 0..4 {
+                let a = gates[rng.below(gates.len() as u64) as usize];
+                let b = gates[rng.below(gates.len() as u64) as usize];
+                swap_nodes(&mut swapped, a, b);
+            }
+
+            // Either every repair is right or the matcher says where it got stuck, on an
+            // adder that really is broken
+            match repair_adder(&swapped) {
+                Ok(repairs) => {
+                    for repair in &repairs {
+                        swap_nodes(&mut swapped, repair.swap.0, repair.swap.1);
+                    }
+                    let circuit = Circuit::new(&swapped).expect("Repaired adder has no cycles");
+                    assert!(verify_adder(&circuit, 0, 50, 1).is_ok());
+                    repaired += 1;
+                }
+                Err(error) => {
+                    assert!(error.bit <= 45, "{error}");
+                    assert!(Circuit::new(&swapped)
+                        .map_or(true, |circuit| verify_adder(&circuit, 0, 50, 1).is_err()));
+                }
+            }
+        }
+        assert!(
+            repaired >= 70,
+            "Only {repaired} of 80 swapped adders were repaired"
+        );
+    }
+
+    #[test]
+    fn test_verify_adder() {
+        let input = adder(6);
+        let (mut nodes, _) = parse_input(&input);
+        let circuit = Circuit::new(&nodes).unwrap();
+        assert!(verify_adder(&circuit, 12, 0, 1).is_ok());
+
+        swap_nodes(&mut nodes, "z03", "c03");
+        let circuit = Circuit::new(&nodes).unwrap();
+        let mismatch = verify_adder(&circuit, 12, 0, 1).expect_err("Swapped adder is wrong");
+
+        assert_eq!(BigUint::default(), mismatch.x);
+        assert_eq!(BigUint::from(8u8), mismatch.y);
+        assert_eq!(vec![3, 4], mismatch.bits);
+        assert!(mismatch.cone.contains(&"c03") && mismatch.cone.contains(&"z04"));
+        assert!(!mismatch
+            .cone
+            .iter()
+            .any(|g| g.starts_with('x') || *g == "z05"));
+
+        let mismatch = verify_adder(&circuit, 0, 100, 1).expect_err("Corner cases catch it");
+        assert!(mismatch.bits.contains(&3) || mismatch.bits.contains(&4));
+
+        // Asking for more exhaustive bits than fit in the operands falls back to sampling
+        let wide = adder(64);
+        let (nodes, _) = parse_input(&wide);
+        let circuit = Circuit::new(&nodes).unwrap();
+        assert!(verify_adder(&circuit, usize::MAX / 2, 20, 1).is_ok());
+    }
+
+    #[test]
+    fn test_extra_gates() {
+        let input = "x00: 1
+y00: 0
+
+x00 NAND y00 -> a
+x00 NOR y00 -> b
+x00 XNOR y00 -> c
+NOT x00 -> d
+1 -> e
+0 -> f
+a AND e -> z00
+b OR f -> z01
+c XOR e -> z02
+d OR f -> z03";
+
+        let (nodes, values) = parse_input(input);
+        assert_eq!(BigUint::from(0b0101u8), part1(&nodes, &values).unwrap());
+
+        let verilog = to_verilog(&nodes, "gates");
+        assert!(verilog.contains("    assign a = ~(x00 & y00);\n"));
+        assert!(verilog.contains("    assign d = ~x00;\n"));
+        assert!(verilog.contains("    assign e = 1'b1;\n"));
+        assert_eq!(vec!["x00", "y00"], input_wires(&nodes));
+    }
+
+    #[test]
+    fn test_wide_adder() {
+        let input = adder(100);
+        let (nodes, mut values) = parse_input(&input);
+        values.insert("x99", true);
+        values.insert("y99", true);
+        values.insert("x00", true);
+
+        let expected = (BigUint::from(1u8) << 100) + 1u8;
+        assert_eq!(expected, part1(&nodes, &values).unwrap());
+
+        let circuit = Circuit::new(&nodes).unwrap();
+        assert!(verify_adder(&circuit, 16, 200, 7).is_ok());
     }
 }
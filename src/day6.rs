@@ -1,4 +1,5 @@
-﻿use std::collections::{HashMap, HashSet};
+﻿use crate::util::bit_set::BitSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
 type Pos = (i32, i32);
@@ -20,7 +21,11 @@ fn parse_input(input: &str) -> (Pos, HashSet<Pos>, Pos) {
         }
     }
 
-    (guard_pos, obstacles, (lines[0].len() as i32, lines.len() as i32))
+    (
+        guard_pos,
+        obstacles,
+        (lines[0].len() as i32, lines.len() as i32),
+    )
 }
 
 fn add((x0, y0): Pos, (x1, y1): Pos) -> Pos {
@@ -35,15 +40,16 @@ fn turn_right((dx, dy): Pos) -> Pos {
     (-dy, dx)
 }
 
-fn turn_left((dx, dy): Pos) -> Pos {
-    (dy, -dx)
-}
-
 fn in_bounds((px, py): Pos, (width, height): Pos) -> bool {
     px >= 0 && py >= 0 && px < width && py < height
 }
 
-fn part1(guard_pos: Pos, forward: Pos, obstacles: &HashSet<Pos>, size: Pos) -> Option<HashMap<Pos, Vec<Pos>>> {
+fn part1(
+    guard_pos: Pos,
+    forward: Pos,
+    obstacles: &HashSet<Pos>,
+    size: Pos,
+) -> Option<HashMap<Pos, Vec<Pos>>> {
     let mut visited: HashMap<Pos, Vec<Pos>> = HashMap::new();
     let mut forward = forward;
     visited.insert(guard_pos, vec![forward]);
@@ -60,77 +66,247 @@ fn part1(guard_pos: Pos, forward: Pos, obstacles: &HashSet<Pos>, size: Pos) -> O
             current_pos = next_pos;
         }
 
-        if visited.get(&current_pos).map(|dir| dir.contains(&forward)).unwrap_or(false) {
+        if visited
+            .get(&current_pos)
+            .map(|dir| dir.contains(&forward))
+            .unwrap_or(false)
+        {
             return None;
         }
 
-        visited.entry(current_pos)
+        visited
+            .entry(current_pos)
             .and_modify(|v| v.push(forward))
             .or_insert(vec![forward]);
     }
 }
 
-fn backtrack(pos: Pos, forward: Pos, obstacles: &HashSet<Pos>, size: Pos, visited: &mut HashMap<Pos, Vec<Pos>>) {
-    let mut forward = forward;
-    let mut current_pos = pos;
+/// Up, right, down, left, so turning right is moving one step along the array
+const DIRECTIONS: [Pos; 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
 
-    loop {
-        if visited.get(&current_pos).map(|p| p.contains(&forward)).unwrap_or(false) {
-            return;
+/// For every cell and direction, the cell the guard stops on before walking into an
+/// obstacle, or None if they walk off the map instead.
+struct JumpTable {
+    size: Pos,
+    stops: [Vec<Option<Pos>>; 4],
+}
+
+impl JumpTable {
+    fn new(obstacles: &HashSet<Pos>, size: Pos) -> JumpTable {
+        let (width, height) = size;
+        let mut cells: Vec<Pos> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect();
+
+        let stops = DIRECTIONS.map(|(dx, dy)| {
+            // Fill in the cell ahead before the one behind it
+            cells.sort_by_key(|&(x, y)| -(x * dx + y * dy));
+            let mut stops = vec![None; (width * height) as usize];
+            for &pos in &cells {
+                let next = add(pos, (dx, dy));
+                stops[index(pos, size)] = if !in_bounds(next, size) {
+                    None
+                } else if obstacles.contains(&next) {
+                    Some(pos)
+                } else {
+                    stops[index(next, size)]
+                };
+            }
+            stops
+        });
+
+        JumpTable { size, stops }
+    }
+
+    /// Where the guard stops walking from `pos` in direction `dir`, with an extra
+    /// obstacle at `block`.
+    fn stop(&self, pos: Pos, dir: usize, block: Pos) -> Option<Pos> {
+        let (dx, dy) = DIRECTIONS[dir];
+        let stop = self.stops[dir][index(pos, self.size)];
+
+        let (bx, by) = subtract(block, pos);
+        let block_dist = bx * dx + by * dy;
+        let in_line = (bx, by) == (dx * block_dist, dy * block_dist);
+        if !in_line || block_dist <= 0 {
+            return stop;
         }
 
-        visited.entry(current_pos)
-            .and_modify(|v| v.push(forward))
-            .or_insert(vec![forward]);
+        match stop {
+            Some((sx, sy)) if (sx - pos.0) * dx + (sy - pos.1) * dy < block_dist => stop,
+            _ => Some(subtract(block, (dx, dy))),
+        }
+    }
 
-        let next_pos = subtract(current_pos, forward);
+    /// Whether the guard walks in a loop from `pos` heading in direction `dir` once an
+    /// obstacle is put at `block`. Only the cells where the guard turns are visited, and
+    /// `seen` holds each (cell, direction) they stopped at.
+    fn loops(&self, pos: Pos, dir: usize, block: Pos, seen: &mut BitSet) -> bool {
+        seen.clear();
+        let (mut pos, mut dir) = (pos, dir);
+
+        while let Some(stop) = self.stop(pos, dir, block) {
+            if !seen.insert(index(stop, self.size) * 4 + dir) {
+                return true;
+            }
+            pos = stop;
+            dir = (dir + 1) % 4;
+        }
+
+        false
+    }
+}
+
+fn index((x, y): Pos, (width, _): Pos) -> usize {
+    (y * width + x) as usize
+}
+
+/// Every cell where one extra obstacle traps the guard in a loop, in the order the guard
+/// reaches them. The guard walks their route once, and each cell they are about to enter
+/// for the first time is tried as an obstacle, checking for a loop from where they stand
+/// rather than from the start.
+fn loop_obstacles(guard_pos: Pos, obstacles: &HashSet<Pos>, size: Pos) -> Vec<Pos> {
+    let table = JumpTable::new(obstacles, size);
+    let mut seen = BitSet::new((size.0 * size.1) as usize * 4);
+    let mut walked = BitSet::new((size.0 * size.1) as usize);
+    walked.insert(index(guard_pos, size));
+
+    let mut res = Vec::new();
+    let mut dir = 0;
+    let mut current_pos = guard_pos;
+
+    loop {
+        let next_pos = add(current_pos, DIRECTIONS[dir]);
         if !in_bounds(next_pos, size) {
-            return;
+            return res;
         }
 
         if obstacles.contains(&next_pos) {
-            forward = turn_left(forward);
-        } else {
-            current_pos = next_pos;
+            dir = (dir + 1) % 4;
+            continue;
+        }
+
+        if walked.insert(index(next_pos, size))
+            && table.loops(current_pos, dir, next_pos, &mut seen)
+        {
+            res.push(next_pos);
         }
+        current_pos = next_pos;
     }
 }
 
 fn part2(guard_pos: Pos, obstacles: &HashSet<Pos>, size: Pos) -> i32 {
-    let mut new_obstacles = HashSet::new();
-    let mut visited: HashMap<Pos, Vec<Pos>> = HashMap::new();
+    loop_obstacles(guard_pos, obstacles, size).len() as i32
+}
 
-    let mut forward = (0, -1);
-    let mut current_pos = guard_pos;
+/// The guard's walk from the start until they leave the map or start repeating themselves.
+pub struct Route {
+    /// Every cell the guard stands on in order, with the direction they leave it in
+    pub path: Vec<(Pos, usize)>,
+    /// Indices into `path` of the cells where the guard turned
+    pub turns: Vec<usize>,
+    /// Index into `path` where the guard's loop starts, None if they leave the map
+    pub loop_start: Option<usize>,
+}
 
-    backtrack(current_pos, forward, &obstacles, size, &mut visited);
+impl Route {
+    /// Walks the guard from `guard_pos` facing up, with an extra obstacle at `block`.
+    pub fn walk(guard_pos: Pos, obstacles: &HashSet<Pos>, size: Pos, block: Option<Pos>) -> Route {
+        let blocked = |pos: Pos| obstacles.contains(&pos) || Some(pos) == block;
+        let mut route = Route {
+            path: Vec::new(),
+            turns: Vec::new(),
+            loop_start: None,
+        };
+        let mut seen = HashMap::new();
+        let (mut pos, mut dir) = (guard_pos, 0);
+
+        loop {
+            let mut turns = 0;
+            while turns < 4 && blocked(add(pos, DIRECTIONS[dir])) {
+                dir = (dir + 1) % 4;
+                turns += 1;
+            }
 
-    loop {
-        visited.entry(current_pos)
-            .and_modify(|v| v.push(forward))
-            .or_insert(vec![forward]);
+            if let Some(&start) = seen.get(&(pos, dir)) {
+                route.loop_start = Some(start);
+                return route;
+            }
+            seen.insert((pos, dir), route.path.len());
+            if turns > 0 {
+                route.turns.push(route.path.len());
+            }
+            route.path.push((pos, dir));
 
-        let next_pos = add(current_pos, forward);
-        if !in_bounds(next_pos, size) {
-            return new_obstacles.len() as i32;
+            // Boxed in on all four sides, so turning on the spot forever
+            if turns == 4 {
+                route.loop_start = Some(route.path.len() - 1);
+                return route;
+            }
+
+            let next = add(pos, DIRECTIONS[dir]);
+            if !in_bounds(next, size) {
+                return route;
+            }
+            pos = next;
         }
+    }
 
-        if obstacles.contains(&next_pos) {
-            forward = turn_right(forward);
-            backtrack(current_pos, forward, &obstacles, size, &mut visited);
-        } else {
-            current_pos = next_pos;
+    /// The part of the path the guard repeats forever, if they are stuck in a loop.
+    pub fn cycle(&self) -> Option<&[(Pos, usize)]> {
+        self.loop_start.map(|start| &self.path[start..])
+    }
+
+    pub fn cells(&self) -> HashSet<Pos> {
+        self.path.iter().map(|&(pos, _)| pos).collect()
+    }
+
+    /// Draws the map with `#` for obstacles, `O` for `block` and an arrow on each cell of
+    /// `path`, or `+` where the guard turned or crossed their own path.
+    pub fn render(
+        path: &[(Pos, usize)],
+        obstacles: &HashSet<Pos>,
+        size: Pos,
+        block: Option<Pos>,
+    ) -> String {
+        let mut directions: HashMap<Pos, Vec<usize>> = HashMap::new();
+        for (i, &(pos, dir)) in path.iter().enumerate() {
+            let dirs = directions.entry(pos).or_default();
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+            // A turn shows up as the direction the guard arrived in
+            if let Some(&(_, prev_dir)) = i.checked_sub(1).map(|prev| &path[prev]) {
+                if !dirs.contains(&prev_dir) {
+                    dirs.push(prev_dir);
+                }
+            }
         }
 
-        if visited.get(&current_pos).map(|dir| dir.contains(&turn_right(forward))).unwrap_or(false) {
-            let new_obstacle = add(current_pos, forward);
-            if new_obstacle != guard_pos {
-                new_obstacles.insert(new_obstacle);
+        let mut res = String::new();
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                res.push(match directions.get(&(x, y)).map(|dirs| dirs.as_slice()) {
+                    _ if Some((x, y)) == block => 'O',
+                    _ if obstacles.contains(&(x, y)) => '#',
+                    Some(&[dir]) => ['^', '>', 'v', '<'][dir],
+                    Some(_) => '+',
+                    None => '.',
+                });
             }
+            res.push('\n');
         }
+        res
     }
 }
 
+/// The loop the guard gets stuck in for each obstacle that traps them.
+pub fn loops(guard_pos: Pos, obstacles: &HashSet<Pos>, size: Pos) -> Vec<(Pos, Route)> {
+    loop_obstacles(guard_pos, obstacles, size)
+        .into_iter()
+        .map(|block| (block, Route::walk(guard_pos, obstacles, size, Some(block))))
+        .collect()
+}
+
 fn part2_v2(guard_pos: Pos, obstacles: &HashSet<Pos>, size: Pos) -> i32 {
     let mut new_obstacles = HashSet::new();
     let mut obstacles = obstacles.clone();
@@ -144,35 +320,57 @@ fn part2_v2(guard_pos: Pos, obstacles: &HashSet<Pos>, size: Pos) -> i32 {
             return new_obstacles.len() as i32;
         }
 
-        if obstacles.contains(&next_pos) {
-            forward = turn_right(forward);
-        } else {
-            current_pos = next_pos;
-        }
-
-        let test_obstacle = add(current_pos, forward);
+        // Try the cell ahead before stepping, so the first cell in front of the guard is
+        // tried too
+        let test_obstacle = next_pos;
         if test_obstacle != guard_pos && obstacles.insert(test_obstacle) {
             if part1(guard_pos, (0, -1), &obstacles, size) == None {
                 new_obstacles.insert(test_obstacle);
             }
             obstacles.remove(&test_obstacle);
         }
+
+        if obstacles.contains(&next_pos) {
+            forward = turn_right(forward);
+        } else {
+            current_pos = next_pos;
+        }
     }
 }
 
 pub fn day6() {
-    let input = fs::read_to_string("inputs/day6.txt")
-        .expect("Could not read input file");
+    let input = fs::read_to_string("inputs/day6.txt").expect("Could not read input file");
 
     let (guard_pos, obstacles, size) = parse_input(&input);
 
-    println!("Part 1: {}", part1(guard_pos, (0, -1), &obstacles, size).unwrap().len());
-    println!("Part 2: {}", part2_v2(guard_pos, &obstacles, size));
+    println!(
+        "Part 1: {}",
+        part1(guard_pos, (0, -1), &obstacles, size).unwrap().len()
+    );
+    println!("Part 2: {}", part2(guard_pos, &obstacles, size));
+
+    let route = Route::walk(guard_pos, &obstacles, size, None);
+    let longest = loops(guard_pos, &obstacles, size)
+        .into_iter()
+        .max_by_key(|(_, route)| route.cycle().map_or(0, |cycle| cycle.len()));
+    println!(
+        "Route: {} steps with {} turns",
+        route.path.len(),
+        route.turns.len()
+    );
+    if let Some((block, route)) = longest {
+        println!(
+            "Longest loop: {} steps from an obstacle at {:?}",
+            route.cycle().unwrap().len(),
+            block
+        );
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::rng::Rng;
 
     #[test]
     pub fn test_p1() {
@@ -187,7 +385,10 @@ mod tests {
 #.........
 ......#...";
         let (guard_pos, obstacles, size) = parse_input(&input);
-        assert_eq!(41, part1(guard_pos, (0, -1), &obstacles, size).unwrap().len());
+        assert_eq!(
+            41,
+            part1(guard_pos, (0, -1), &obstacles, size).unwrap().len()
+        );
     }
 
     #[test]
@@ -205,4 +406,150 @@ mod tests {
         let (guard_pos, obstacles, size) = parse_input(&input);
         assert_eq!(6, part2_v2(guard_pos, &obstacles, size));
     }
-}
\ No newline at end of file
+
+    const SAMPLE: &str = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+
+    #[test]
+    pub fn test_block_in_front() {
+        // The only trap is the cell right in front of the guard's start, which the
+        // reference walk missed while it tried the cell ahead after each step
+        let input = "..#..
+.^..#
+#....
+...#.";
+        let (guard_pos, obstacles, size) = parse_input(input);
+        assert_eq!(1, part2_v2(guard_pos, &obstacles, size));
+        assert_eq!(vec![(1, 0)], loop_obstacles(guard_pos, &obstacles, size));
+    }
+
+    #[test]
+    pub fn test_jump_table() {
+        let (guard_pos, obstacles, size) = parse_input(SAMPLE);
+        assert_eq!(6, part2(guard_pos, &obstacles, size));
+
+        let table = JumpTable::new(&obstacles, size);
+        assert_eq!(Some((4, 1)), table.stop(guard_pos, 0, (-1, -1)));
+        assert_eq!(Some((4, 3)), table.stop(guard_pos, 0, (4, 2)));
+        assert_eq!(None, table.stop((4, 1), 3, (-1, -1)));
+        // Blocks behind the guard or beyond the real obstacle change nothing
+        assert_eq!(Some((4, 1)), table.stop(guard_pos, 0, (4, 8)));
+        assert_eq!(Some((8, 1)), table.stop((4, 1), 1, (9, 1)));
+    }
+
+    #[test]
+    pub fn test_random_maps() {
+        let mut rng = Rng::new(6);
+        let mut checked = 0;
+
+        while checked < 200 {
+            let size = (3 + rng.below(10) as i32, 3 + rng.below(10) as i32);
+            let guard_pos = (
+                rng.below(size.0 as u64) as i32,
+                rng.below(size.1 as u64) as i32,
+            );
+            let obstacles: HashSet<Pos> = (0..size.1)
+                .flat_map(|y| (0..size.0).map(move |x| (x, y)))
+                .filter(|&pos| pos != guard_pos)
+                .filter(|_| rng.below(100) < 15)
+                .collect();
+
+            // The reference walk never ends if the guard is already stuck in a loop
+            if part1(guard_pos, (0, -1), &obstacles, size).is_none() {
+                continue;
+            }
+            assert_eq!(
+                part2_v2(guard_pos, &obstacles, size),
+                part2(guard_pos, &obstacles, size)
+            );
+            checked += 1;
+        }
+    }
+
+    #[test]
+    pub fn test_route() {
+        let (guard_pos, obstacles, size) = parse_input(SAMPLE);
+        let route = Route::walk(guard_pos, &obstacles, size, None);
+
+        assert_eq!(None, route.loop_start);
+        assert_eq!(41, route.cells().len());
+        assert_eq!(((4, 6), 0), route.path[0]);
+        assert_eq!(((7, 9), 2), *route.path.last().unwrap());
+        assert_eq!(
+            vec![
+                (4, 1),
+                (8, 1),
+                (8, 6),
+                (2, 6),
+                (2, 4),
+                (6, 4),
+                (6, 8),
+                (1, 8),
+                (1, 7),
+                (7, 7)
+            ],
+            route
+                .turns
+                .iter()
+                .map(|&i| route.path[i].0)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "....#.....
+....+>>>+#
+....^...v.
+..#.^...v.
+..+>+>+#v.
+..^.^.v.v.
+.#+<+<+<+.
+.+>>>>++#.
+#+<<<<+v..
+......#v..
+",
+            Route::render(&route.path, &obstacles, size, None)
+        );
+    }
+
+    #[test]
+    pub fn test_loops() {
+        let (guard_pos, obstacles, size) = parse_input(SAMPLE);
+        let loops = loops(guard_pos, &obstacles, size);
+
+        let mut blocks: Vec<Pos> = loops.iter().map(|&(block, _)| block).collect();
+        blocks.sort();
+        assert_eq!(vec![(1, 8), (3, 6), (3, 8), (6, 7), (7, 7), (7, 9)], blocks);
+
+        for (block, route) in &loops {
+            let cycle = route.cycle().unwrap();
+            // Stepping on from the end of the cycle leads back to its start
+            let &(last, dir) = cycle.last().unwrap();
+            let (first, _) = cycle[0];
+            assert!(first == last || first == add(last, DIRECTIONS[dir]));
+            assert!(!route.cells().contains(block));
+        }
+
+        let (_, route) = loops.iter().find(|(block, _)| *block == (3, 6)).unwrap();
+        assert_eq!(
+            "....#.....
+....+>>>+#
+....^...v.
+..#.^...v.
+....^..#v.
+....^...v.
+.#.O^<<<+.
+........#.
+#.........
+......#...
+",
+            Route::render(route.cycle().unwrap(), &obstacles, size, Some((3, 6)))
+        );
+    }
+}
@@ -12,9 +12,6 @@ mod day19;
 mod day2;
 mod day20;
 mod day21;
-mod day21_2;
-mod day21_3;
-mod day21_4;
 mod day22;
 mod day23;
 mod day24;
@@ -27,7 +24,7 @@ mod day7;
 mod day8;
 mod day9;
 mod util;
-use day21_4::*;
+use day21::*;
 
 fn main() {
     day21();
@@ -0,0 +1,145 @@
+use crate::util::matrix::pow_by_squaring;
+use std::ops::Mul;
+
+/// Square matrix over GF(2) with at most 64 rows, one `u64` per row. Bit `col` of row
+/// `row` says whether output bit `row` depends on input bit `col`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitMatrix {
+    size: usize,
+    rows: Vec<u64>,
+}
+
+impl BitMatrix {
+    pub fn zero(size: usize) -> BitMatrix {
+        assert!(size <= 64, "BitMatrix holds at most 64 bits");
+        BitMatrix {
+            size,
+            rows: vec![0; size],
+        }
+    }
+
+    pub fn identity(size: usize) -> BitMatrix {
+        let mut res = BitMatrix::zero(size);
+        for (i, row) in res.rows.iter_mut().enumerate() {
+            *row = 1 << i;
+        }
+        res
+    }
+
+    /// Matrix of a map that is linear over GF(2), found by feeding it each unit vector.
+    pub fn from_linear(size: usize, f: impl Fn(u64) -> u64) -> BitMatrix {
+        let mut res = BitMatrix::zero(size);
+        for col in 0..size {
+            let image = f(1 << col);
+            for (row, bits) in res.rows.iter_mut().enumerate() {
+                *bits |= ((image >> row) & 1) << col;
+            }
+        }
+        res
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        (self.rows[row] >> col) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: bool) {
+        if value {
+            self.rows[row] |= 1 << col;
+        } else {
+            self.rows[row] &= !(1 << col);
+        }
+    }
+
+    pub fn apply(&self, v: u64) -> u64 {
+        self.rows.iter().enumerate().fold(0, |res, (i, row)| {
+            res | (((row & v).count_ones() as u64 & 1) << i)
+        })
+    }
+
+    /// Power over GF(2), the matrix of applying the map `exp` times in a row.
+    pub fn pow(&self, exp: u64) -> BitMatrix {
+        pow_by_squaring(self, BitMatrix::identity(self.size), exp, |a, b| a * b)
+    }
+
+    /// Inverse by Gauss-Jordan elimination, or None if the matrix is singular.
+    pub fn inverse(&self) -> Option<BitMatrix> {
+        let mut left = self.rows.clone();
+        let mut right = BitMatrix::identity(self.size).rows;
+
+        for col in 0..self.size {
+            let pivot = (col..self.size).find(|&row| (left[row] >> col) & 1 == 1)?;
+            left.swap(col, pivot);
+            right.swap(col, pivot);
+
+            for row in 0..self.size {
+                if row != col && (left[row] >> col) & 1 == 1 {
+                    left[row] ^= left[col];
+                    right[row] ^= right[col];
+                }
+            }
+        }
+
+        Some(BitMatrix {
+            size: self.size,
+            rows: right,
+        })
+    }
+}
+
+impl Mul for &BitMatrix {
+    type Output = BitMatrix;
+
+    fn mul(self, rhs: &BitMatrix) -> BitMatrix {
+        assert_eq!(self.size, rhs.size);
+        let mut res = BitMatrix::zero(self.size);
+
+        // Row i of the product is the XOR of the rows of rhs picked out by row i of self
+        for (res_row, row) in res.rows.iter_mut().zip(&self.rows) {
+            for (k, rhs_row) in rhs.rows.iter().enumerate() {
+                if (row >> k) & 1 == 1 {
+                    *res_row ^= rhs_row;
+                }
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::rng::Rng;
+
+    #[test]
+    fn test_linear_maps() {
+        let rotate = BitMatrix::from_linear(8, |v| ((v << 1) | (v >> 7)) & 0xff);
+        assert_eq!(0b0000_0011, rotate.apply(0b1000_0001));
+        assert_eq!(BitMatrix::identity(8), rotate.pow(8));
+        assert_eq!(rotate.pow(7), rotate.inverse().unwrap());
+        assert!(rotate.get(1, 0) && !rotate.get(0, 0));
+
+        let mut collapse = BitMatrix::identity(4);
+        collapse.set(3, 3, false);
+        assert_eq!(None, collapse.inverse());
+    }
+
+    #[test]
+    fn test_random_inverse() {
+        let mut rng = Rng::new(22);
+        let mut inverted = 0;
+        for _ in 0..50 {
+            let mut m = BitMatrix::zero(24);
+            for row in &mut m.rows {
+                *row = rng.below(1 << 24);
+            }
+            if let Some(inverse) = m.inverse() {
+                assert_eq!(BitMatrix::identity(24), &m * &inverse);
+                assert_eq!(BitMatrix::identity(24), &inverse * &m);
+                inverted += 1;
+            }
+        }
+        // About 29% of random matrices over GF(2) are invertible
+        assert!(inverted > 0);
+    }
+}
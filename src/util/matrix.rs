@@ -0,0 +1,160 @@
+use num_bigint::BigUint;
+use std::ops::Mul;
+
+/// `base` raised to `exp` by repeated squaring, with `O(log exp)` calls to `mul`.
+/// `identity` is what a power of 0 gives.
+pub fn pow_by_squaring<M>(base: &M, identity: M, mut exp: u64, mul: impl Fn(&M, &M) -> M) -> M
+where
+    M: Clone,
+{
+    let mut res = identity;
+    let mut base = base.clone();
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = mul(&res, &base);
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mul(&base, &base);
+        }
+    }
+
+    res
+}
+
+/// Square matrix of big integers, used to step linear recurrences many times at once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matrix {
+    size: usize,
+    cells: Vec<BigUint>,
+}
+
+impl Matrix {
+    pub fn zero(size: usize) -> Matrix {
+        Matrix {
+            size,
+            cells: vec![BigUint::default(); size * size],
+        }
+    }
+
+    pub fn identity(size: usize) -> Matrix {
+        let mut res = Matrix::zero(size);
+        for i in 0..size {
+            res.cells[i * size + i] = BigUint::from(1u8);
+        }
+        res
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &BigUint {
+        &self.cells[row * self.size + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: BigUint) {
+        self.cells[row * self.size + col] = value;
+    }
+
+    /// Integer power, whose cells grow without bound as `exp` does.
+    pub fn pow(&self, exp: u64) -> Matrix {
+        self.power(exp, None)
+    }
+
+    /// Raises the matrix to `exp` with every cell reduced modulo `modulus`, which keeps
+    /// the cells small no matter how large `exp` gets.
+    pub fn pow_mod(&self, exp: u64, modulus: &BigUint) -> Matrix {
+        self.power(exp, Some(modulus))
+    }
+
+    fn power(&self, exp: u64, modulus: Option<&BigUint>) -> Matrix {
+        pow_by_squaring(
+            &self.reduced(modulus),
+            Matrix::identity(self.size).reduced(modulus),
+            exp,
+            |a, b| a.product(b, modulus),
+        )
+    }
+
+    fn reduced(&self, modulus: Option<&BigUint>) -> Matrix {
+        match modulus {
+            Some(modulus) => Matrix {
+                size: self.size,
+                cells: self.cells.iter().map(|cell| cell % modulus).collect(),
+            },
+            None => self.clone(),
+        }
+    }
+
+    fn product(&self, rhs: &Matrix, modulus: Option<&BigUint>) -> Matrix {
+        assert_eq!(self.size, rhs.size);
+        let size = self.size;
+        let mut res = Matrix::zero(size);
+
+        for row in 0..size {
+            for k in 0..size {
+                let a = self.get(row, k);
+                if *a == BigUint::default() {
+                    continue;
+                }
+                for col in 0..size {
+                    res.cells[row * size + col] += a * rhs.get(k, col);
+                }
+            }
+            if let Some(modulus) = modulus {
+                for cell in &mut res.cells[row * size..(row + 1) * size] {
+                    *cell %= modulus;
+                }
+            }
+        }
+
+        res
+    }
+
+    pub fn mul_vec(&self, v: &[BigUint]) -> Vec<BigUint> {
+        assert_eq!(self.size, v.len());
+        (0..self.size)
+            .map(|row| (0..self.size).map(|col| self.get(row, col) * &v[col]).sum())
+            .collect()
+    }
+}
+
+impl Mul for &Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: &Matrix) -> Matrix {
+        self.product(rhs, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fibonacci() {
+        let mut m = Matrix::zero(2);
+        m.set(0, 0, BigUint::from(1u8));
+        m.set(0, 1, BigUint::from(1u8));
+        m.set(1, 0, BigUint::from(1u8));
+
+        let v = m.pow(90).mul_vec(&[BigUint::from(1u8), BigUint::default()]);
+        assert_eq!(BigUint::from(2880067194370816120u64), v[1]);
+        assert_eq!(Matrix::identity(2), m.pow(0));
+    }
+
+    #[test]
+    fn test_pow_mod() {
+        let mut m = Matrix::zero(2);
+        m.set(0, 0, BigUint::from(1u8));
+        m.set(0, 1, BigUint::from(1u8));
+        m.set(1, 0, BigUint::from(1u8));
+
+        // Fibonacci numbers repeat modulo 10 with a period of 60
+        let modulus = BigUint::from(10u8);
+        assert_eq!(m.pow(7).reduced(Some(&modulus)), m.pow_mod(67, &modulus));
+        assert_eq!(
+            BigUint::from(5u8),
+            *m.pow_mod(10u64.pow(18) + 25, &modulus).get(0, 1)
+        );
+        assert_eq!(Matrix::identity(2), m.pow_mod(0, &modulus));
+    }
+}
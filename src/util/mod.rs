@@ -1,4 +1,9 @@
-﻿pub mod metagraph;
+﻿pub mod bit_matrix;
+pub mod bit_set;
+pub mod matrix;
+pub mod metagraph;
 pub mod print_grid;
+pub mod rng;
 pub mod str_util;
+pub mod union_find;
 pub mod vec2;
@@ -0,0 +1,61 @@
+/// Disjoint sets over `0..len`, merged by rank with path halving.
+#[derive(Clone, Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+        }
+    }
+
+    /// Representative of the set holding `i`.
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Merges the sets holding `a` and `b`, returning whether they were apart.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        let (low, high) = if self.rank[a] < self.rank[b] { (a, b) } else { (b, a) };
+        self.parent[low] = high;
+        if self.rank[low] == self.rank[high] {
+            self.rank[high] += 1;
+        }
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union() {
+        let mut sets = UnionFind::new(6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(!sets.union(1, 0));
+        assert!(sets.same(0, 1) && !sets.same(1, 2));
+
+        assert!(sets.union(1, 3));
+        assert!(sets.same(0, 2));
+        assert!(!sets.same(4, 5) && sets.same(5, 5));
+    }
+}